@@ -0,0 +1,280 @@
+use crate::cert::Certificate;
+use crate::error::{InputError, NetworkError, ParseError, Result};
+use crate::openssl;
+use crate::tls::{self, PeerChain};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use log::{debug, info};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Where inputs come from besides files: TLS handshakes, stdin and password prompts. `inspect`
+/// and everything built on it go through this, so tests can swap in canned chains.
+pub trait Backend: fmt::Debug + Send + Sync {
+    /// Performs a handshake with `address` (a `host:port` pair), sending `server_name` as SNI.
+    fn fetch(&self, server_name: &str, address: &str) -> Result<PeerChain>;
+
+    /// Whatever was piped on stdin, byte for byte, or `None` when nothing was.
+    fn stdin(&self) -> Result<Option<Vec<u8>>, InputError>;
+
+    /// Asks for the password protecting `input`, or `None` when there is nobody to ask.
+    fn password(&self, input: &str) -> Result<Option<String>, InputError>;
+}
+
+/// Real handshakes over rustls and the process's own stdin.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Native;
+
+impl Backend for Native {
+    fn fetch(&self, server_name: &str, address: &str) -> Result<PeerChain> {
+        tls::fetch_peer_chain(server_name, address)
+    }
+
+    fn stdin(&self) -> Result<Option<Vec<u8>>, InputError> {
+        read_stdin()
+    }
+
+    fn password(&self, input: &str) -> Result<Option<String>, InputError> {
+        prompt_password(input)
+    }
+}
+
+/// Handshakes by running `openssl s_client`, for cross-checking the native backend against
+/// what openssl sees. Results are normalised to the same names the native backend uses.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Openssl;
+
+impl Backend for Openssl {
+    fn fetch(&self, server_name: &str, address: &str) -> Result<PeerChain> {
+        openssl::fetch_peer_chain(server_name, address)
+    }
+
+    fn stdin(&self) -> Result<Option<Vec<u8>>, InputError> {
+        read_stdin()
+    }
+
+    fn password(&self, input: &str) -> Result<Option<String>, InputError> {
+        prompt_password(input)
+    }
+}
+
+// a terminal means nobody piped anything in, and reading would just block waiting for input
+fn read_stdin() -> Result<Option<Vec<u8>>, InputError> {
+    let stdin = io::stdin();
+    if stdin.is_terminal() {
+        return Ok(None);
+    }
+    let mut buffer = Vec::new();
+    stdin
+        .lock()
+        .read_to_end(&mut buffer)
+        .map_err(InputError::read(Path::new("stdin")))?;
+    if buffer.iter().all(u8::is_ascii_whitespace) {
+        Ok(None)
+    } else {
+        Ok(Some(buffer))
+    }
+}
+
+// rpassword talks to the terminal directly, so this works even with the input piped on stdin
+fn prompt_password(input: &str) -> Result<Option<String>, InputError> {
+    match rpassword::prompt_password(format!("Password for {}: ", input)) {
+        Ok(password) => Ok(Some(password)),
+        Err(e) => {
+            debug!("cannot prompt for a password: {}", e);
+            Ok(None)
+        }
+    }
+}
+
+/// Canned chains keyed by SNI name, stdin and password; never touches the network or the terminal.
+#[derive(Debug, Default)]
+pub struct Fake {
+    chains: HashMap<String, PeerChain>,
+    stdin: Option<Vec<u8>>,
+    password: Option<String>,
+    fetched: Mutex<Vec<(String, String)>>,
+}
+
+impl Fake {
+    pub fn new() -> Fake {
+        Fake::default()
+    }
+
+    pub fn with_chain(mut self, server_name: &str, chain: PeerChain) -> Fake {
+        self.chains.insert(server_name.to_string(), chain);
+        self
+    }
+
+    /// Serves the certificates in `pem` to `server_name` as if over TLS 1.3.
+    pub fn with_pem(self, server_name: &str, pem: &[u8]) -> Result<Fake, ParseError> {
+        let chain = PeerChain {
+            certificates: Certificate::all_from_pem(pem)?
+                .into_iter()
+                .map(|certificate| certificate.der)
+                .collect(),
+            protocol: "TLSv1_3".to_string(),
+            cipher_suite: "TLS13_AES_128_GCM_SHA256".to_string(),
+            alpn: None,
+        };
+        Ok(self.with_chain(server_name, chain))
+    }
+
+    pub fn with_stdin(mut self, stdin: impl Into<Vec<u8>>) -> Fake {
+        self.stdin = Some(stdin.into());
+        self
+    }
+
+    pub fn with_password(mut self, password: &str) -> Fake {
+        self.password = Some(password.to_string());
+        self
+    }
+
+    /// Every `(server_name, address)` pair fetched so far, in order.
+    pub fn fetched(&self) -> Vec<(String, String)> {
+        self.fetched.lock().unwrap().clone()
+    }
+}
+
+impl Backend for Fake {
+    fn fetch(&self, server_name: &str, address: &str) -> Result<PeerChain> {
+        self.fetched
+            .lock()
+            .unwrap()
+            .push((server_name.to_string(), address.to_string()));
+        self.chains.get(server_name).cloned().ok_or_else(|| {
+            NetworkError::Connect {
+                address: address.to_string(),
+                port: port(address),
+                source: io::ErrorKind::ConnectionRefused.into(),
+            }
+            .into()
+        })
+    }
+
+    fn stdin(&self) -> Result<Option<Vec<u8>>, InputError> {
+        Ok(self
+            .stdin
+            .clone()
+            .filter(|stdin| !stdin.iter().all(u8::is_ascii_whitespace)))
+    }
+
+    fn password(&self, _input: &str) -> Result<Option<String>, InputError> {
+        Ok(self.password.clone())
+    }
+}
+
+fn port(address: &str) -> u16 {
+    address
+        .rsplit(':')
+        .next()
+        .and_then(|port| port.parse().ok())
+        .unwrap_or_default()
+}
+
+/// Replays chains saved by [`Recorder`] from `<dir>/<server name>.pem`. Each file holds
+/// `key: value` session lines followed by the chain as PEM blocks.
+#[derive(Debug, Clone)]
+pub struct Fixtures {
+    dir: PathBuf,
+}
+
+impl Fixtures {
+    pub fn new(dir: impl Into<PathBuf>) -> Fixtures {
+        Fixtures { dir: dir.into() }
+    }
+
+    fn path(&self, server_name: &str) -> PathBuf {
+        self.dir.join(format!("{}.pem", server_name))
+    }
+}
+
+impl Backend for Fixtures {
+    fn fetch(&self, server_name: &str, _address: &str) -> Result<PeerChain> {
+        let path = self.path(server_name);
+        let data = fs::read_to_string(&path).map_err(InputError::read(&path))?;
+        let session: HashMap<&str, &str> = data
+            .lines()
+            .take_while(|line| !line.starts_with("-----BEGIN"))
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .collect();
+        let field = |key: &str| session.get(key).map(|value| value.to_string());
+        Ok(PeerChain {
+            certificates: Certificate::all_from_pem(data.as_bytes())?
+                .into_iter()
+                .map(|certificate| certificate.der)
+                .collect(),
+            protocol: field("protocol").unwrap_or_default(),
+            cipher_suite: field("cipher_suite").unwrap_or_default(),
+            alpn: field("alpn").filter(|alpn| alpn != "none"),
+        })
+    }
+
+    fn stdin(&self) -> Result<Option<Vec<u8>>, InputError> {
+        read_stdin()
+    }
+
+    fn password(&self, input: &str) -> Result<Option<String>, InputError> {
+        prompt_password(input)
+    }
+}
+
+/// Passes everything through to `inner`, saving each fetched chain as a [`Fixtures`] file.
+#[derive(Debug)]
+pub struct Recorder<B> {
+    inner: B,
+    fixtures: Fixtures,
+}
+
+impl<B: Backend> Recorder<B> {
+    pub fn new(inner: B, dir: impl Into<PathBuf>) -> Recorder<B> {
+        Recorder {
+            inner,
+            fixtures: Fixtures::new(dir),
+        }
+    }
+}
+
+impl<B: Backend> Backend for Recorder<B> {
+    fn fetch(&self, server_name: &str, address: &str) -> Result<PeerChain> {
+        let chain = self.inner.fetch(server_name, address)?;
+        let path = self.fixtures.path(server_name);
+        fs::write(&path, fixture(&chain)).map_err(|source| InputError::Write {
+            path: path.clone(),
+            source,
+        })?;
+        info!("recorded {} to {}", server_name, path.display());
+        Ok(chain)
+    }
+
+    fn stdin(&self) -> Result<Option<Vec<u8>>, InputError> {
+        self.inner.stdin()
+    }
+
+    fn password(&self, input: &str) -> Result<Option<String>, InputError> {
+        self.inner.password(input)
+    }
+}
+
+fn fixture(chain: &PeerChain) -> String {
+    let mut out = format!(
+        "protocol: {}\ncipher_suite: {}\nalpn: {}\n",
+        chain.protocol,
+        chain.cipher_suite,
+        chain.alpn.as_deref().unwrap_or("none")
+    );
+    for der in &chain.certificates {
+        out += "-----BEGIN CERTIFICATE-----\n";
+        for line in STANDARD.encode(der).as_bytes().chunks(64) {
+            out += &String::from_utf8_lossy(line);
+            out += "\n";
+        }
+        out += "-----END CERTIFICATE-----\n";
+    }
+    out
+}
@@ -0,0 +1,149 @@
+use crate::cert::{format_time, Certificate};
+use serde::Serialize;
+use std::fmt;
+use time::OffsetDateTime;
+
+/// How a certificate relates to the one presented after it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Link {
+    /// The issuer matches the subject of the next certificate.
+    Matches,
+    /// The issuer does not match the subject of the next certificate.
+    Mismatch { next_subject: String },
+    /// Last in the chain and issued by itself.
+    SelfSigned,
+    /// Last in the chain and its issuer was not presented.
+    IssuerNotPresented,
+}
+
+impl fmt::Display for Link {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Link::Matches => write!(f, "ok, issued by next certificate"),
+            Link::Mismatch { next_subject } => write!(f, "BROKEN, next certificate is {}", next_subject),
+            Link::SelfSigned => write!(f, "self-signed"),
+            Link::IssuerNotPresented => write!(f, "issuer not presented"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChainEntry {
+    pub depth: usize,
+    pub subject: String,
+    pub issuer: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub not_after: OffsetDateTime,
+    pub days_remaining: i64,
+    pub link: Link,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(transparent)]
+pub struct Chain {
+    pub entries: Vec<ChainEntry>,
+}
+
+impl Chain {
+    pub fn new(certificates: &[Certificate]) -> Chain {
+        let now = OffsetDateTime::now_utc();
+        let entries = certificates
+            .iter()
+            .enumerate()
+            .map(|(depth, certificate)| {
+                let link = match certificates.get(depth + 1) {
+                    Some(next) if next.subject == certificate.issuer => Link::Matches,
+                    Some(next) => Link::Mismatch {
+                        next_subject: next.subject.clone(),
+                    },
+                    None if certificate.issuer == certificate.subject => Link::SelfSigned,
+                    None => Link::IssuerNotPresented,
+                };
+                ChainEntry {
+                    depth,
+                    subject: certificate.subject.clone(),
+                    issuer: certificate.issuer.clone(),
+                    not_after: certificate.not_after,
+                    days_remaining: (certificate.not_after - now).whole_days(),
+                    link,
+                }
+            })
+            .collect();
+        Chain { entries }
+    }
+
+    /// True when every certificate is issued by the one presented after it.
+    pub fn is_linked(&self) -> bool {
+        self.entries
+            .iter()
+            .all(|entry| !matches!(entry.link, Link::Mismatch { .. }))
+    }
+}
+
+impl fmt::Display for Chain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines: Vec<String> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                format!(
+                    "depth {}: {}\n    issuer:  {}\n    expires: {} ({} days)\n    link:    {}",
+                    entry.depth,
+                    entry.subject,
+                    entry.issuer,
+                    format_time(entry.not_after),
+                    entry.days_remaining,
+                    entry.link
+                )
+            })
+            .collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::{BasicConstraints, CertificateParams, CertifiedIssuer, DnType, IsCa, KeyPair};
+
+    fn ca(name: &str) -> CertifiedIssuer<'static, KeyPair> {
+        let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        params.distinguished_name.push(DnType::CommonName, name);
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        CertifiedIssuer::self_signed(params, KeyPair::generate().unwrap()).unwrap()
+    }
+
+    fn leaf(issuer: &CertifiedIssuer<'static, KeyPair>) -> Certificate {
+        let mut params = CertificateParams::new(vec!["leaf.test".to_string()]).unwrap();
+        params.distinguished_name.push(DnType::CommonName, "leaf.test");
+        let cert = params.signed_by(&KeyPair::generate().unwrap(), issuer).unwrap();
+        Certificate::from_der(cert.der()).unwrap()
+    }
+
+    #[test]
+    fn links_each_certificate_to_the_next() {
+        let root = ca("Test Root");
+        let chain = Chain::new(&[leaf(&root), Certificate::from_der(root.der()).unwrap()]);
+        assert_eq!(chain.entries[0].link, Link::Matches);
+        assert_eq!(chain.entries[1].link, Link::SelfSigned);
+        assert!(chain.is_linked());
+    }
+
+    #[test]
+    fn flags_misordered_and_missing_issuers() {
+        let root = ca("Test Root");
+        let other = ca("Other Root");
+        let chain = Chain::new(&[leaf(&root), Certificate::from_der(other.der()).unwrap()]);
+        assert_eq!(
+            chain.entries[0].link,
+            Link::Mismatch {
+                next_subject: "CN=Other Root".to_string()
+            }
+        );
+        assert!(!chain.is_linked());
+
+        let chain = Chain::new(&[leaf(&root)]);
+        assert_eq!(chain.entries[0].link, Link::IssuerNotPresented);
+    }
+}
@@ -0,0 +1,227 @@
+use crate::cert::{format_time, Certificate, San};
+use crate::error::Result;
+use crate::inspect::{inspect, Options};
+use colored::Colorize;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::fmt;
+
+#[derive(Debug, Serialize)]
+pub struct Difference {
+    pub field: String,
+    pub left: Option<String>,
+    pub right: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Comparison {
+    pub left: String,
+    pub right: String,
+    pub differences: Vec<Difference>,
+}
+
+impl Comparison {
+    pub fn matches(&self) -> bool {
+        self.differences.is_empty()
+    }
+}
+
+impl Serialize for Comparison {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Comparison", 4)?;
+        state.serialize_field("left", &self.left)?;
+        state.serialize_field("right", &self.right)?;
+        state.serialize_field("matches", &self.matches())?;
+        state.serialize_field("differences", &self.differences)?;
+        state.end()
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.matches() {
+            return write!(f, "{} and {} match", self.left, self.right);
+        }
+        writeln!(f, "{} {}", "---".red(), self.left.red())?;
+        write!(f, "{} {}", "+++".green(), self.right.green())?;
+        for difference in &self.differences {
+            write!(f, "\n{}", difference.field.bold())?;
+            if let Some(left) = &difference.left {
+                for line in left.lines() {
+                    write!(f, "\n  {}", format!("- {}", line).red())?;
+                }
+            }
+            if let Some(right) = &difference.right {
+                for line in right.lines() {
+                    write!(f, "\n  {}", format!("+ {}", line).green())?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn diff_field(differences: &mut Vec<Difference>, field: &str, left: Option<String>, right: Option<String>) {
+    if left != right {
+        differences.push(Difference {
+            field: field.to_string(),
+            left,
+            right,
+        });
+    }
+}
+
+fn compare_certificates(left: &Certificate, right: &Certificate) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    let join_sans = |sans: &[San]| sans.iter().map(|san| san.to_string()).collect::<Vec<_>>().join("\n");
+    let fields = [
+        ("subject", left.subject.clone(), right.subject.clone()),
+        ("issuer", left.issuer.clone(), right.issuer.clone()),
+        ("serial", left.serial.clone(), right.serial.clone()),
+        ("sans", join_sans(&left.sans), join_sans(&right.sans)),
+        (
+            "not before",
+            format_time(left.not_before),
+            format_time(right.not_before),
+        ),
+        ("not after", format_time(left.not_after), format_time(right.not_after)),
+        ("key", left.public_key.to_string(), right.public_key.to_string()),
+        (
+            "signature algorithm",
+            left.signature_algorithm.clone(),
+            right.signature_algorithm.clone(),
+        ),
+    ];
+    for (field, l, r) in fields {
+        diff_field(&mut differences, field, Some(l), Some(r));
+    }
+
+    let render = |certificate: &Certificate, name: &str| {
+        certificate.extension(name).map(|extension| match extension.critical {
+            true => format!("critical\n{}", extension.value),
+            false => extension.value.clone(),
+        })
+    };
+    let mut names: Vec<&String> = Vec::new();
+    for extension in left.extensions.iter().chain(&right.extensions) {
+        // subjectAltName is already compared entry by entry above
+        if extension.name != "subjectAltName" && !names.contains(&&extension.name) {
+            names.push(&extension.name);
+        }
+    }
+    for name in names {
+        diff_field(
+            &mut differences,
+            &format!("extension {}", name),
+            render(left, name),
+            render(right, name),
+        );
+    }
+
+    diff_field(
+        &mut differences,
+        "fingerprint",
+        Some(left.fingerprint.clone()),
+        Some(right.fingerprint.clone()),
+    );
+    differences
+}
+
+/// Compares the leaves of two inputs field by field, or every certificate position by position
+/// with `--all` or `--chain`.
+pub fn compare(domain1: &str, domain2: &str, options: &Options) -> Result<Comparison> {
+    let inspection1 = inspect(domain1, options)?;
+    let inspection2 = inspect(domain2, options)?;
+    let (chain1, chain2) = (inspection1.selected(), inspection2.selected());
+    let mut differences = Vec::new();
+    if !options.selects_all() {
+        differences = compare_certificates(&chain1[0], &chain2[0]);
+    } else {
+        let (length, position) = match options.chain {
+            true => ("chain length", "depth"),
+            false => ("certificate count", "certificate"),
+        };
+        diff_field(
+            &mut differences,
+            length,
+            Some(chain1.len().to_string()),
+            Some(chain2.len().to_string()),
+        );
+        for (index, (certificate1, certificate2)) in chain1.iter().zip(chain2).enumerate() {
+            for mut difference in compare_certificates(certificate1, certificate2) {
+                difference.field = format!("{} {} {}", position, index, difference.field);
+                differences.push(difference);
+            }
+        }
+    }
+    Ok(Comparison {
+        left: domain1.to_string(),
+        right: domain2.to_string(),
+        differences,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::{CertificateParams, DnType, ExtendedKeyUsagePurpose, KeyPair};
+
+    fn certificate(name: &str, server_auth: bool) -> Certificate {
+        let mut params = CertificateParams::new(vec![name.to_string()]).unwrap();
+        params.distinguished_name.push(DnType::CommonName, name);
+        if server_auth {
+            params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+        }
+        let cert = params.self_signed(&KeyPair::generate().unwrap()).unwrap();
+        Certificate::from_der(cert.der()).unwrap()
+    }
+
+    fn fields(differences: &[Difference]) -> Vec<&str> {
+        differences.iter().map(|difference| difference.field.as_str()).collect()
+    }
+
+    #[test]
+    fn finds_no_differences_between_a_certificate_and_itself() {
+        let leaf = certificate("leaf.test", false);
+        let comparison = Comparison {
+            left: "a".to_string(),
+            right: "b".to_string(),
+            differences: compare_certificates(&leaf, &leaf.clone()),
+        };
+        assert!(comparison.matches());
+        assert_eq!(comparison.to_string(), "a and b match");
+        let json = serde_json::to_value(&comparison).unwrap();
+        assert_eq!(json["matches"], true);
+    }
+
+    #[test]
+    fn diffs_fields_sans_and_extensions_present_on_one_side() {
+        let left = certificate("one.test", false);
+        let right = certificate("two.test", true);
+        let differences = compare_certificates(&left, &right);
+        let fields = fields(&differences);
+        for field in ["subject", "issuer", "serial", "sans", "fingerprint"] {
+            assert!(fields.contains(&field), "{} missing from {:?}", field, fields);
+        }
+        assert!(!fields.contains(&"key") && !fields.contains(&"signature algorithm"));
+        assert!(!fields.iter().any(|field| field.contains("subjectAltName")));
+
+        let sans = &differences[fields.iter().position(|field| *field == "sans").unwrap()];
+        assert_eq!(sans.left.as_deref(), Some("DNS:one.test"));
+        assert_eq!(sans.right.as_deref(), Some("DNS:two.test"));
+        let eku = &differences[fields
+            .iter()
+            .position(|field| *field == "extension extendedKeyUsage")
+            .unwrap()];
+        assert_eq!(eku.left, None);
+        assert_eq!(eku.right.as_deref(), Some("serverAuth"));
+
+        let comparison = Comparison {
+            left: "one".to_string(),
+            right: "two".to_string(),
+            differences,
+        };
+        assert!(!comparison.matches());
+        assert_eq!(serde_json::to_value(&comparison).unwrap()["matches"], false);
+    }
+}
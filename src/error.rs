@@ -0,0 +1,137 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Everything that can go wrong before a result can be shown.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    Input(#[from] InputError),
+    #[error(transparent)]
+    Network(#[from] NetworkError),
+    #[error(transparent)]
+    TlsHandshake(#[from] TlsHandshakeError),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error(transparent)]
+    Verify(#[from] VerifyError),
+    #[error("failed to render {format} output: {reason}")]
+    Output { format: &'static str, reason: String },
+}
+
+/// The target, file or flag given on the command line can't be used as-is.
+#[derive(Debug, Error)]
+pub enum InputError {
+    #[error("'{input}' is not an existing file or a valid target ({reason}), and nothing was piped on stdin")]
+    Unrecognized { input: String, reason: String },
+    #[error("nothing was piped on stdin; try `cat cert.pem | ssl inspect -`")]
+    NoStdin,
+    #[error("invalid target '{input}': {reason}")]
+    Target { input: String, reason: String },
+    // clap already quotes the offending value when it reports this
+    #[error("{reason}, expected HOST:PORT:ADDR")]
+    Resolve { input: String, reason: String },
+    #[error("--index {index} is out of range; the input holds {count} certificate(s), numbered from 0")]
+    Index { index: usize, count: usize },
+    #[error("--all does not apply to {command}; pick a certificate with --index N")]
+    SelectAll { command: &'static str },
+    #[error("cannot get the password for '{input}': {reason}")]
+    Password { input: String, reason: String },
+    #[error("cannot get the password for '{input}': ${name} is not set")]
+    PasswordUnset { input: String, name: String },
+    #[error("wrong password for keystore; pass it with --password-env or --password-file")]
+    WrongPassword,
+    #[error("failed to read {}: {source}", path.display())]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("failed to write {}: {source}", path.display())]
+    Write {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+}
+
+impl InputError {
+    /// For `map_err` on filesystem calls, so the failing path ends up in the message.
+    pub fn read(path: &Path) -> impl FnOnce(io::Error) -> InputError + '_ {
+        move |source| InputError::Read {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+}
+
+/// The target couldn't be reached at the TCP level.
+#[derive(Debug, Error)]
+pub enum NetworkError {
+    #[error("failed to resolve {address}: {source}; check the name or pin it with --resolve/--connect")]
+    Resolve {
+        address: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("{address} did not resolve to any addresses")]
+    NoAddresses { address: String },
+    #[error("failed to connect to {address}: {source}; is anything listening on port {port}?")]
+    Connect {
+        address: String,
+        port: u16,
+        #[source]
+        source: io::Error,
+    },
+}
+
+/// A TCP connection was made but no certificate came out of the TLS handshake.
+#[derive(Debug, Error)]
+pub enum TlsHandshakeError {
+    #[error("'{server_name}' can't be sent as SNI: {reason}; pass a DNS name with --servername")]
+    ServerName { server_name: String, reason: String },
+    #[error("failed to set up the TLS client: {0}")]
+    Config(#[from] rustls::Error),
+    #[error("TLS handshake with {address} (SNI {server_name}) failed: {source}; is the port speaking TLS?")]
+    Handshake {
+        address: String,
+        server_name: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("failed to run openssl: {source}; install it or use --backend native")]
+    Openssl {
+        #[source]
+        source: io::Error,
+    },
+    #[error("{address} completed the handshake without presenting a certificate")]
+    NoCertificate { address: String },
+}
+
+/// The bytes handed to us don't contain a certificate we can read.
+#[derive(Debug, Error)]
+pub enum ParseError {
+    #[error("no certificate found in input; expected PEM, DER or base64-encoded DER")]
+    NoCertificate,
+    #[error("failed to decode PEM block: {reason}")]
+    Pem { reason: String },
+    #[error("failed to parse DER certificate: {reason}")]
+    Der { reason: String },
+    #[error("failed to read PKCS#12 archive: {reason}")]
+    Pkcs12 { reason: String },
+    #[error("failed to read PKCS#7 bundle: {reason}")]
+    Pkcs7 { reason: String },
+    #[error("failed to read Java keystore: {reason}")]
+    Keystore { reason: String },
+}
+
+/// Path validation couldn't be attempted, as opposed to a chain that failed it.
+#[derive(Debug, Error)]
+pub enum VerifyError {
+    #[error("no CA certificates found in --ca-file/--ca-dir")]
+    NoTrustAnchors,
+    #[error("failed to load the system trust store: {reason}; pass --ca-file or --ca-dir instead")]
+    SystemStore { reason: String },
+}
@@ -0,0 +1,213 @@
+use crate::cert::Certificate;
+use crate::error::{InputError, ParseError, Result};
+use crate::jks::{self, JCEKS_MAGIC, JKS_MAGIC};
+use crate::keystore::Entry;
+use crate::{pkcs12, pkcs7};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::Serialize;
+use std::fmt;
+use x509_parser::pem::Pem;
+
+// every X.509 certificate is a DER SEQUENCE, so its first byte is always this tag
+const SEQUENCE: u8 = 0x30;
+const INTEGER: u8 = 0x02;
+const OBJECT_IDENTIFIER: u8 = 0x06;
+
+const PKCS7_LABELS: [&str; 2] = ["PKCS7", "CMS"];
+
+/// How a file or stdin encodes its certificates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// `-----BEGIN CERTIFICATE-----` blocks, possibly surrounded by other text.
+    Pem,
+    /// Binary DER, as in most `.der` and `.cer` files.
+    Der,
+    /// DER encoded as base64 without the PEM header and footer.
+    Base64,
+    /// A PKCS#12 (`.p12`, `.pfx`) archive, binary or base64 encoded.
+    Pkcs12,
+    /// A PKCS#7 (`.p7b`, `.p7c`) certificate bundle, binary, base64 or PEM encoded.
+    Pkcs7,
+    /// A Java KeyStore (`.jks`).
+    Jks,
+    /// A Java Cryptography Extension KeyStore (`.jceks`).
+    Jceks,
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Pem => write!(f, "PEM"),
+            Format::Der => write!(f, "DER"),
+            Format::Base64 => write!(f, "base64"),
+            Format::Pkcs12 => write!(f, "PKCS#12"),
+            Format::Pkcs7 => write!(f, "PKCS#7"),
+            Format::Jks => write!(f, "JKS"),
+            Format::Jceks => write!(f, "JCEKS"),
+        }
+    }
+}
+
+fn base64(data: &[u8]) -> Option<Vec<u8>> {
+    let compact: Vec<u8> = data.iter().copied().filter(|b| !b.is_ascii_whitespace()).collect();
+    STANDARD.decode(compact).ok()
+}
+
+fn contains(data: &[u8], needle: &str) -> bool {
+    data.windows(needle.len()).any(|window| window == needle.as_bytes())
+}
+
+// told apart by the first element inside the outer SEQUENCE: a certificate starts with its
+// TBSCertificate SEQUENCE, a PKCS#12 PFX with its INTEGER version and a PKCS#7 ContentInfo
+// with its content type OID
+fn der_format(der: &[u8]) -> Option<Format> {
+    if der.first() != Some(&SEQUENCE) {
+        return None;
+    }
+    let length = *der.get(1)?;
+    let header = match length < 0x80 {
+        true => 2,
+        false => 2 + (length & 0x7f) as usize,
+    };
+    match *der.get(header)? {
+        SEQUENCE => Some(Format::Der),
+        INTEGER => Some(Format::Pkcs12),
+        OBJECT_IDENTIFIER => Some(Format::Pkcs7),
+        _ => None,
+    }
+}
+
+/// Guesses the encoding of `data` from its content alone, ignoring any file extension.
+pub fn detect(data: &[u8]) -> Option<Format> {
+    if data.starts_with(&JKS_MAGIC) {
+        return Some(Format::Jks);
+    } else if data.starts_with(&JCEKS_MAGIC) {
+        return Some(Format::Jceks);
+    }
+    if contains(data, "-----BEGIN") {
+        let pkcs7 = PKCS7_LABELS
+            .iter()
+            .any(|label| contains(data, &format!("-----BEGIN {}-----", label)));
+        return Some(if pkcs7 { Format::Pkcs7 } else { Format::Pem });
+    }
+    match der_format(data) {
+        Some(format) => Some(format),
+        None => match base64(data).and_then(|der| der_format(&der)) {
+            Some(Format::Der) => Some(Format::Base64),
+            format => format,
+        },
+    }
+}
+
+// openssl writes PKCS#7 as PKCS7 blocks, newer tools as CMS; plain CERTIFICATE blocks in the
+// same file are kept, in order
+fn pem_pkcs7(data: &[u8]) -> Result<Vec<Certificate>, ParseError> {
+    let mut certificates = Vec::new();
+    for pem in Pem::iter_from_buffer(data) {
+        let pem = pem.map_err(|e| ParseError::Pem { reason: e.to_string() })?;
+        if PKCS7_LABELS.contains(&pem.label.as_str()) {
+            certificates.extend(pkcs7::certificates(&pem.contents)?);
+        } else if pem.label == "CERTIFICATE" {
+            certificates.push(Certificate::from_der(&pem.contents)?);
+        }
+    }
+    if certificates.is_empty() {
+        return Err(ParseError::NoCertificate);
+    }
+    Ok(certificates)
+}
+
+/// What an input decoded to: its certificates and, for keystores, the entries holding them.
+#[derive(Debug)]
+pub struct Decoded {
+    pub format: Format,
+    pub certificates: Vec<Certificate>,
+    pub entries: Vec<Entry>,
+}
+
+/// Decodes `data`, whichever of the [`Format`]s it is in. `password` is only called for
+/// protected keystores.
+pub fn decode(data: &[u8], password: &mut dyn FnMut() -> Result<String, InputError>) -> Result<Decoded> {
+    let format = detect(data).ok_or(ParseError::NoCertificate)?;
+    let der = match data.first() {
+        Some(&SEQUENCE) => data.to_vec(),
+        _ => base64(data).unwrap_or_default(),
+    };
+    let (certificates, entries) = match format {
+        Format::Pem => (Certificate::all_from_pem(data)?, Vec::new()),
+        Format::Der | Format::Base64 => (Certificate::all_from_der(&der)?, Vec::new()),
+        Format::Pkcs12 => pkcs12::decode(&der, password)?,
+        Format::Pkcs7 if contains(data, "-----BEGIN") => (pem_pkcs7(data)?, Vec::new()),
+        Format::Pkcs7 => (pkcs7::certificates(&der)?, Vec::new()),
+        Format::Jks | Format::Jceks => jks::decode(data, password)?,
+    };
+    Ok(Decoded {
+        format,
+        certificates,
+        entries,
+    })
+}
+
+/// Every certificate in `data`, for where no password can be asked for: PKCS#12 archives must
+/// have an empty one, and Java keystores are read without checking their integrity.
+pub fn certificates(data: &[u8]) -> Result<(Format, Vec<Certificate>)> {
+    let decoded = decode(data, &mut || {
+        Err(InputError::Password {
+            input: "keystore".to_string(),
+            reason: "no password can be given here".to_string(),
+        })
+    })?;
+    Ok((decoded.format, decoded.certificates))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::{CertificateParams, KeyPair};
+
+    fn der(name: &str) -> Vec<u8> {
+        let params = CertificateParams::new(vec![name.to_string()]).unwrap();
+        params
+            .self_signed(&KeyPair::generate().unwrap())
+            .unwrap()
+            .der()
+            .to_vec()
+    }
+
+    #[test]
+    fn detects_each_format_by_content() {
+        let der = der("leaf.test");
+        let pem = format!(
+            "subject=CN = leaf.test\n-----BEGIN CERTIFICATE-----\n{}\n-----END CERTIFICATE-----\n",
+            STANDARD.encode(&der)
+        );
+        let wrapped: Vec<String> = STANDARD
+            .encode(&der)
+            .as_bytes()
+            .chunks(64)
+            .map(|line| String::from_utf8_lossy(line).to_string())
+            .collect();
+        assert_eq!(detect(pem.as_bytes()), Some(Format::Pem));
+        assert_eq!(detect(&der), Some(Format::Der));
+        assert_eq!(detect(wrapped.join("\n").as_bytes()), Some(Format::Base64));
+        assert_eq!(detect(b"this is not a certificate\n"), None);
+        assert_eq!(detect(b"aGVsbG8="), None);
+        assert_eq!(detect(&[0xfe, 0xed, 0xfe, 0xed, 0, 0, 0, 2]), Some(Format::Jks));
+        assert_eq!(detect(&[0xce, 0xce, 0xce, 0xce, 0, 0, 0, 2]), Some(Format::Jceks));
+    }
+
+    #[test]
+    fn reads_concatenated_der_certificates() {
+        let data = [der("one.test"), der("two.test")].concat();
+        let (format, parsed) = certificates(&data).unwrap();
+        assert_eq!(format, Format::Der);
+        let sans: Vec<String> = parsed.iter().map(|c| c.sans[0].to_string()).collect();
+        assert_eq!(sans, ["DNS:one.test", "DNS:two.test"]);
+        assert!(matches!(
+            certificates(&data[..40]),
+            Err(crate::Error::Parse(ParseError::Der { .. }))
+        ));
+    }
+}
@@ -0,0 +1,219 @@
+use crate::cert::{Certificate, San};
+use crate::error::Result;
+use crate::inspect::{inspect, Options};
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::net::IpAddr;
+
+/// Why a single presented identifier did or did not match the reference name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Candidate {
+    pub identifier: String,
+    pub matched: bool,
+    pub reason: String,
+}
+
+/// The outcome of checking a name against a certificate under RFC 6125 rules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameMatch {
+    pub name: String,
+    pub candidates: Vec<Candidate>,
+}
+
+impl NameMatch {
+    pub fn is_match(&self) -> bool {
+        self.candidates.iter().any(|candidate| candidate.matched)
+    }
+
+    pub fn matched_by(&self) -> Option<&str> {
+        self.candidates
+            .iter()
+            .find(|candidate| candidate.matched)
+            .map(|candidate| candidate.identifier.as_str())
+    }
+}
+
+impl Serialize for NameMatch {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("NameMatch", 4)?;
+        state.serialize_field("name", &self.name)?;
+        state.serialize_field("matched", &self.is_match())?;
+        state.serialize_field("matched_by", &self.matched_by())?;
+        state.serialize_field("candidates", &self.candidates)?;
+        state.end()
+    }
+}
+
+impl fmt::Display for NameMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for candidate in &self.candidates {
+            let mark = if candidate.matched { "+" } else { "-" };
+            writeln!(f, "{} {}: {}", mark, candidate.identifier, candidate.reason)?;
+        }
+        match self.matched_by() {
+            Some(identifier) => write!(f, "{} matches ({})", self.name, identifier),
+            None => write!(f, "{} does not match", self.name),
+        }
+    }
+}
+
+fn normalize(name: &str) -> String {
+    name.trim_end_matches('.').to_ascii_lowercase()
+}
+
+fn match_dns(pattern: &str, name: &str) -> (bool, String) {
+    let pattern = normalize(pattern);
+    let labels: Vec<&str> = pattern.split('.').collect();
+    if let Some(position) = labels.iter().skip(1).position(|label| label.contains('*')) {
+        return (
+            false,
+            format!(
+                "wildcard in label {} ignored, only the left-most label may be a wildcard",
+                position + 2
+            ),
+        );
+    }
+    match labels[0] {
+        "*" => {
+            if labels.len() < 3 {
+                return (
+                    false,
+                    "wildcard ignored, it must be followed by at least two labels".to_string(),
+                );
+            }
+            let suffix = &pattern[2..];
+            match name.split_once('.') {
+                Some((label, rest)) if !label.is_empty() && rest == suffix => {
+                    (true, format!("wildcard matches the single label '{}'", label))
+                }
+                Some((_, rest)) if rest.ends_with(&format!(".{}", suffix)) => {
+                    (false, "wildcard matches exactly one label, not several".to_string())
+                }
+                _ => (false, format!("name is not a direct subdomain of {}", suffix)),
+            }
+        }
+        label if label.contains('*') => (false, "partial wildcards like 'w*' are not permitted".to_string()),
+        _ if pattern == name => (true, "exact match".to_string()),
+        _ => (false, "different name".to_string()),
+    }
+}
+
+/// Checks `name` against the certificate's SANs. IP names only match IP SANs, compared as
+/// addresses; the subject CN is consulted only with `legacy_cn` and when no DNS SANs exist.
+pub fn check(certificate: &Certificate, name: &str, legacy_cn: bool) -> NameMatch {
+    let ip = name
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+        .ok();
+    let normalized = normalize(name);
+    let mut candidates = Vec::new();
+
+    for san in &certificate.sans {
+        let (matched, reason) = match (san, ip) {
+            (San::Ip(value), Some(ip)) => match value.parse::<IpAddr>() {
+                Ok(address) if address == ip => (true, "same address".to_string()),
+                Ok(_) => (false, "different address".to_string()),
+                Err(_) => (false, "unparseable IP SAN".to_string()),
+            },
+            (San::Ip(_), None) => (false, "IP SAN, but the name is a hostname".to_string()),
+            (San::Dns(_), Some(_)) => (false, "DNS SAN, but the name is an IP address".to_string()),
+            (San::Dns(pattern), None) => match_dns(pattern, &normalized),
+            _ => (false, format!("{} SANs are not used for server identity", san.kind())),
+        };
+        candidates.push(Candidate {
+            identifier: san.to_string(),
+            matched,
+            reason,
+        });
+    }
+
+    if let Some(cn) = &certificate.common_name {
+        let has_dns_sans = certificate.sans.iter().any(|san| matches!(san, San::Dns(_)));
+        let (matched, reason) = if !legacy_cn {
+            (
+                false,
+                "subject CN ignored, enable --legacy-cn for CN fallback".to_string(),
+            )
+        } else if has_dns_sans {
+            (false, "subject CN ignored because DNS SANs are present".to_string())
+        } else if ip.is_some() {
+            (false, "subject CN is never used for IP addresses".to_string())
+        } else {
+            let (matched, reason) = match_dns(cn, &normalized);
+            (matched, format!("legacy CN fallback, {}", reason))
+        };
+        candidates.push(Candidate {
+            identifier: format!("CN:{}", cn),
+            matched,
+            reason,
+        });
+    }
+
+    NameMatch {
+        name: name.to_string(),
+        candidates,
+    }
+}
+
+/// Checks the certificate [`Inspection::selected`](crate::Inspection::selected) in `domain`
+/// against `hostname`.
+pub fn matches(domain: &str, hostname: &str, options: &Options) -> Result<NameMatch> {
+    let inspection = inspect(domain, options)?;
+    Ok(check(&inspection.selected()[0], hostname, options.legacy_cn))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn certificate(sans: Vec<San>, common_name: Option<&str>) -> Certificate {
+        let key = rcgen::generate_simple_self_signed(vec!["placeholder.test".to_string()]).unwrap();
+        let mut certificate = Certificate::from_der(key.cert.der()).unwrap();
+        certificate.sans = sans;
+        certificate.common_name = common_name.map(str::to_string);
+        certificate
+    }
+
+    fn dns(name: &str) -> San {
+        San::Dns(name.to_string())
+    }
+
+    #[test]
+    fn wildcard_matches_only_one_left_most_label() {
+        let cert = certificate(vec![dns("*.example.com")], None);
+        assert!(check(&cert, "www.example.com", false).is_match());
+        assert!(check(&cert, "WWW.Example.COM.", false).is_match());
+        assert!(!check(&cert, "example.com", false).is_match());
+        assert!(!check(&cert, "a.b.example.com", false).is_match());
+    }
+
+    #[test]
+    fn rejects_partial_and_non_left_most_wildcards() {
+        let cert = certificate(vec![dns("w*.example.com"), dns("www.*.com"), dns("*.com")], None);
+        let result = check(&cert, "www.example.com", false);
+        assert!(!result.is_match());
+        assert!(result.candidates[0].reason.contains("partial"));
+        assert!(result.candidates[1].reason.contains("left-most"));
+        assert!(result.candidates[2].reason.contains("two labels"));
+    }
+
+    #[test]
+    fn compares_ip_sans_as_addresses() {
+        let cert = certificate(vec![San::Ip("2001:db8::1".to_string()), dns("10.0.0.1")], None);
+        assert!(check(&cert, "2001:DB8:0:0:0:0:0:1", false).is_match());
+        assert!(check(&cert, "[2001:db8::1]", false).is_match());
+        assert!(!check(&cert, "10.0.0.1", false).is_match());
+    }
+
+    #[test]
+    fn cn_fallback_requires_legacy_flag_and_no_dns_sans() {
+        let cert = certificate(vec![], Some("legacy.example.com"));
+        assert!(!check(&cert, "legacy.example.com", false).is_match());
+        assert!(check(&cert, "legacy.example.com", true).is_match());
+
+        let cert = certificate(vec![dns("other.example.com")], Some("legacy.example.com"));
+        assert!(!check(&cert, "legacy.example.com", true).is_match());
+    }
+}
@@ -0,0 +1,301 @@
+use crate::backend::{Backend, Native};
+use crate::cert::Certificate;
+use crate::chain::Chain;
+use crate::error::{InputError, ParseError, Result};
+use crate::format::{self, Format};
+use crate::hostname::{self, NameMatch};
+use crate::keystore::{Entry, Password};
+use crate::target::{FetchOptions, Target};
+use crate::tls::PeerChain;
+use log::{debug, info, warn};
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+/// How to interpret an input argument. `Auto` tries a file, then a `host[:port]` target,
+/// then stdin; `-` always means stdin.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InputKind {
+    #[default]
+    Auto,
+    File,
+    Stdin,
+    Host,
+}
+
+/// Which certificates of a multi-certificate input to operate on. `--chain` implies `All`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Selection {
+    /// The first certificate, which for hosts and chain files is the leaf.
+    #[default]
+    Leaf,
+    /// The certificate at this zero-based position in the input.
+    Index(usize),
+    /// Every certificate, in input order.
+    All,
+}
+
+#[derive(Debug)]
+enum InputType {
+    Domain(Target),
+    File(String),
+    Stdin(Vec<u8>),
+}
+
+fn stdin(backend: &dyn Backend) -> Result<InputType, InputError> {
+    backend.stdin()?.map(InputType::Stdin).ok_or(InputError::NoStdin)
+}
+
+fn input_type(input: &str, kind: InputKind, backend: &dyn Backend) -> Result<InputType, InputError> {
+    let path = Path::new(input);
+    match kind {
+        InputKind::File => return Ok(InputType::File(input.to_string())),
+        InputKind::Host => return Ok(InputType::Domain(Target::parse(input)?)),
+        InputKind::Stdin => return stdin(backend),
+        InputKind::Auto if input == "-" => return stdin(backend),
+        InputKind::Auto => {}
+    }
+    if path.exists() && fs::metadata(path).map_err(InputError::read(path))?.is_file() {
+        Ok(InputType::File(input.to_string()))
+    } else {
+        match Target::parse(input) {
+            Ok(target) => Ok(InputType::Domain(target)),
+            Err(e) => match backend.stdin()? {
+                Some(buffer) => Ok(InputType::Stdin(buffer)),
+                None => Err(InputError::Unrecognized {
+                    input: input.to_string(),
+                    reason: match e {
+                        InputError::Target { reason, .. } => reason,
+                        e => e.to_string(),
+                    },
+                }),
+            },
+        }
+    }
+}
+
+/// Connects to `target`, honouring `--servername`, `--connect` and `--resolve`, and returns what it presented.
+pub fn fetch(target: &Target, options: &Options) -> Result<PeerChain> {
+    options
+        .backend
+        .fetch(&options.fetch.server_name(target), &options.fetch.address(target)?)
+}
+
+/// What to inspect beyond the certificates themselves.
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub backend: Arc<dyn Backend>,
+    pub input: InputKind,
+    pub fetch: FetchOptions,
+    pub chain: bool,
+    pub select: Selection,
+    pub password: Password,
+    pub legacy_cn: bool,
+}
+
+impl Options {
+    /// Whether every certificate is selected, either with `--all` or as a `--chain`.
+    pub fn selects_all(&self) -> bool {
+        self.chain || self.select == Selection::All
+    }
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            backend: Arc::new(Native),
+            input: InputKind::Auto,
+            fetch: FetchOptions::default(),
+            chain: false,
+            select: Selection::Leaf,
+            password: Password::Prompt,
+            legacy_cn: false,
+        }
+    }
+}
+
+/// Everything learned from one input: the certificates, for hosts the session and name check,
+/// and for files and stdin their format and any keystore entries.
+#[derive(Debug)]
+pub struct Inspection {
+    pub server_name: Option<String>,
+    pub name_match: Option<NameMatch>,
+    pub session: Option<PeerChain>,
+    pub format: Option<Format>,
+    pub entries: Vec<Entry>,
+    pub certificates: Vec<Certificate>,
+    pub chain: bool,
+    pub select: Selection,
+}
+
+impl Inspection {
+    /// The leaf alone, the certificate picked with `--index`, or all of them with `--all` or
+    /// `--chain`.
+    pub fn selected(&self) -> &[Certificate] {
+        match self.select {
+            _ if self.chain => &self.certificates,
+            Selection::All => &self.certificates,
+            Selection::Index(index) => &self.certificates[index..=index],
+            Selection::Leaf => &self.certificates[..1],
+        }
+    }
+
+    /// The alias of the keystore entry holding the certificate at `position`, if any.
+    pub fn alias(&self, position: usize) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|entry| entry.certificates.contains(&position))
+            .and_then(|entry| entry.alias.as_deref())
+    }
+
+    /// Where the selected certificates start in the input, for labelling them.
+    pub fn offset(&self) -> usize {
+        match self.select {
+            Selection::Index(index) if !self.chain => index,
+            _ => 0,
+        }
+    }
+}
+
+impl Inspection {
+    /// The session and hostname check, then a summary of each selected certificate, or the
+    /// full text dump when `raw` is set.
+    pub fn render(&self, raw: bool) -> String {
+        let mut sections = Vec::new();
+        if let Some(session) = &self.session {
+            let mut header = session.to_string();
+            if let Some(name_match) = &self.name_match {
+                match name_match.matched_by() {
+                    Some(identifier) => {
+                        header += &format!("\n    Hostname: {} matches {}", name_match.name, identifier)
+                    }
+                    None => header += &format!("\n    Hostname: {} DOES NOT MATCH", name_match.name),
+                }
+            }
+            sections.push(header);
+        }
+        if !self.entries.is_empty() {
+            let entries: Vec<String> = self
+                .entries
+                .iter()
+                .map(|entry| format!("    {}", entry.to_string().replace('\n', "\n    ")))
+                .collect();
+            let format = self.format.map(|format| format!(" ({})", format)).unwrap_or_default();
+            sections.push(format!("Entries{}:\n{}", format, entries.join("\n")));
+        }
+        if self.chain {
+            sections.push(Chain::new(&self.certificates).to_string());
+        }
+        let labelled = self.certificates.len() > 1 && !self.chain;
+        for (index, certificate) in self.selected().iter().enumerate() {
+            let text = match raw {
+                true => certificate.to_string(),
+                false => certificate.summary().to_string(),
+            };
+            sections.push(match labelled {
+                true => format!("certificate {}:\n{}", self.offset() + index, text),
+                false => text,
+            });
+        }
+        sections.join("\n\n")
+    }
+}
+
+impl fmt::Display for Inspection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render(false))
+    }
+}
+
+impl Serialize for Inspection {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Inspection", 7)?;
+        state.serialize_field("server_name", &self.server_name)?;
+        state.serialize_field("name_match", &self.name_match)?;
+        state.serialize_field("session", &self.session)?;
+        state.serialize_field("format", &self.format)?;
+        state.serialize_field("entries", &self.entries)?;
+        state.serialize_field("chain", &self.chain.then(|| Chain::new(&self.certificates)))?;
+        state.serialize_field("certificates", self.selected())?;
+        state.end()
+    }
+}
+
+fn decode(input: &str, data: &[u8], options: &Options) -> Result<format::Decoded> {
+    let backend = options.backend.as_ref();
+    let decoded = format::decode(data, &mut || options.password.resolve(input, backend))?;
+    debug!("{}: read as {}", input, decoded.format);
+    Ok(decoded)
+}
+
+/// Reads certificates from a file, a `host[:port]` target or stdin, in that order of preference.
+pub fn inspect(input: &str, options: &Options) -> Result<Inspection> {
+    let (server_name, session, format, entries, certificates) =
+        match input_type(input, options.input, options.backend.as_ref())? {
+            InputType::Domain(target) => {
+                let chain = fetch(&target, options)?;
+                let certificates = chain
+                    .certificates
+                    .iter()
+                    .map(|der| Certificate::from_der(der))
+                    .collect::<Result<Vec<_>, ParseError>>()?;
+                let server_name = options.fetch.server_name(&target);
+                (Some(server_name), Some(chain), None, Vec::new(), certificates)
+            }
+            InputType::File(file_path) => {
+                let data = fs::read(&file_path).map_err(InputError::read(Path::new(&file_path)))?;
+                let decoded = decode(input, &data, options)?;
+                (None, None, Some(decoded.format), decoded.entries, decoded.certificates)
+            }
+            InputType::Stdin(stdin_content) => {
+                let decoded = decode(input, &stdin_content, options)?;
+                (None, None, Some(decoded.format), decoded.entries, decoded.certificates)
+            }
+        };
+    if certificates.is_empty() {
+        return Err(ParseError::NoCertificate.into());
+    }
+    debug!(
+        "{}: {} certificate(s), leaf {}",
+        input,
+        certificates.len(),
+        certificates[0].subject
+    );
+    if let Selection::Index(index) = options.select {
+        if index >= certificates.len() {
+            return Err(InputError::Index {
+                index,
+                count: certificates.len(),
+            }
+            .into());
+        }
+    } else if session.is_none() && certificates.len() > 1 && !options.selects_all() {
+        info!(
+            "{} holds {} certificates; using the first, pass --all or --index N for the others",
+            input,
+            certificates.len()
+        );
+    }
+    let name_match = server_name
+        .as_deref()
+        .map(|name| hostname::check(&certificates[0], name, options.legacy_cn));
+    if let Some(name_match) = name_match.as_ref().filter(|name_match| !name_match.is_match()) {
+        warn!(
+            "certificate presented by {} is not valid for {}",
+            input, name_match.name
+        );
+    }
+    Ok(Inspection {
+        server_name,
+        name_match,
+        session,
+        format,
+        entries,
+        certificates,
+        chain: options.chain,
+        select: options.select,
+    })
+}
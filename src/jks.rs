@@ -0,0 +1,332 @@
+use crate::cert::Certificate;
+use crate::error::{InputError, ParseError, Result};
+use crate::keystore::{self, Entry, EntryKind};
+use log::warn;
+use sha1::{Digest, Sha1};
+use std::rc::Rc;
+
+pub(crate) const JKS_MAGIC: [u8; 4] = [0xfe, 0xed, 0xfe, 0xed];
+pub(crate) const JCEKS_MAGIC: [u8; 4] = [0xce, 0xce, 0xce, 0xce];
+
+const PRIVATE_KEY: u32 = 1;
+const TRUSTED_CERTIFICATE: u32 = 2;
+const SECRET_KEY: u32 = 3;
+
+// keytool's integrity check: SHA-1 over the password as UTF-16BE, this phrase and the store
+const WHITENER: &[u8] = b"Mighty Aphrodite";
+
+fn error(reason: impl ToString) -> ParseError {
+    ParseError::Keystore {
+        reason: reason.to_string(),
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], ParseError> {
+        let bytes = self
+            .data
+            .get(self.position..self.position.saturating_add(length))
+            .ok_or_else(|| error(format!("truncated at byte {}", self.position)))?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, ParseError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, ParseError> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, ParseError> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, ParseError> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    // Java's modified UTF-8 only differs from UTF-8 for NUL and astral characters
+    fn utf(&mut self) -> Result<String, ParseError> {
+        let length = self.u16()? as usize;
+        Ok(String::from_utf8_lossy(self.take(length)?).to_string())
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], ParseError> {
+        let length = self.u32()? as usize;
+        self.take(length)
+    }
+
+    fn certificate(&mut self, version: u32) -> Result<Certificate, ParseError> {
+        if version == 2 {
+            let kind = self.utf()?;
+            if kind != "X.509" {
+                return Err(error(format!("unsupported certificate type {}", kind)));
+            }
+        }
+        Certificate::from_der(self.bytes()?)
+    }
+}
+
+/// A class description from a Java serialization stream, as far as skipping instances needs.
+struct Class {
+    name: String,
+    flags: u8,
+    fields: Vec<u8>,
+    parent: Option<Rc<Class>>,
+}
+
+// JCEKS stores secret keys as serialized `SealedObject`s, which carry no length, so skipping
+// one means walking the stream (Java Object Serialization Specification, chapter 6)
+struct Serialized<'r, 'a> {
+    reader: &'r mut Reader<'a>,
+    handles: Vec<Option<Rc<Class>>>,
+}
+
+impl Serialized<'_, '_> {
+    const NULL: u8 = 0x70;
+    const REFERENCE: u8 = 0x71;
+    const CLASS_DESC: u8 = 0x72;
+    const OBJECT: u8 = 0x73;
+    const STRING: u8 = 0x74;
+    const ARRAY: u8 = 0x75;
+    const CLASS: u8 = 0x76;
+    const BLOCK_DATA: u8 = 0x77;
+    const END_BLOCK_DATA: u8 = 0x78;
+    const RESET: u8 = 0x79;
+    const BLOCK_DATA_LONG: u8 = 0x7a;
+    const LONG_STRING: u8 = 0x7c;
+    const ENUM: u8 = 0x7e;
+    const BASE_HANDLE: u32 = 0x7e_0000;
+    const WRITE_METHOD: u8 = 0x01;
+    const EXTERNALIZABLE: u8 = 0x04;
+
+    fn skip(reader: &mut Reader) -> Result<(), ParseError> {
+        if reader.take(4)? != [0xac, 0xed, 0x00, 0x05] {
+            return Err(error("secret key entry is not a Java serialization stream"));
+        }
+        Serialized {
+            reader,
+            handles: Vec::new(),
+        }
+        .object()
+    }
+
+    fn peek(&self) -> Result<u8, ParseError> {
+        self.reader
+            .data
+            .get(self.reader.position)
+            .copied()
+            .ok_or_else(|| error("truncated serialized object"))
+    }
+
+    fn handle(&mut self) -> Result<Option<Rc<Class>>, ParseError> {
+        let handle = self.reader.u32()?.wrapping_sub(Self::BASE_HANDLE) as usize;
+        self.handles
+            .get(handle)
+            .cloned()
+            .ok_or_else(|| error("dangling reference in serialized object"))
+    }
+
+    fn class(&mut self) -> Result<Option<Rc<Class>>, ParseError> {
+        match self.reader.u8()? {
+            Self::NULL => Ok(None),
+            Self::REFERENCE => self.handle(),
+            Self::CLASS_DESC => {
+                let name = self.reader.utf()?;
+                self.reader.u64()?;
+                let handle = self.handles.len();
+                self.handles.push(None);
+                let flags = self.reader.u8()?;
+                let mut fields = Vec::new();
+                for _ in 0..self.reader.u16()? {
+                    let kind = self.reader.u8()?;
+                    self.reader.utf()?;
+                    if kind == b'L' || kind == b'[' {
+                        self.object()?;
+                    }
+                    fields.push(kind);
+                }
+                self.annotation()?;
+                let parent = self.class()?;
+                let class = Rc::new(Class {
+                    name,
+                    flags,
+                    fields,
+                    parent,
+                });
+                self.handles[handle] = Some(class.clone());
+                Ok(Some(class))
+            }
+            tag => Err(error(format!("unsupported class description 0x{:02x}", tag))),
+        }
+    }
+
+    fn annotation(&mut self) -> Result<(), ParseError> {
+        while self.peek()? != Self::END_BLOCK_DATA {
+            self.object()?;
+        }
+        self.reader.u8()?;
+        Ok(())
+    }
+
+    fn value(&mut self, kind: u8) -> Result<(), ParseError> {
+        match kind {
+            b'B' | b'Z' => self.reader.take(1).map(drop),
+            b'C' | b'S' => self.reader.take(2).map(drop),
+            b'I' | b'F' => self.reader.take(4).map(drop),
+            b'J' | b'D' => self.reader.take(8).map(drop),
+            b'L' | b'[' => self.object(),
+            kind => Err(error(format!("unsupported field type {}", kind as char))),
+        }
+    }
+
+    fn object(&mut self) -> Result<(), ParseError> {
+        if self.peek()? == Self::CLASS_DESC {
+            return self.class().map(drop);
+        }
+        match self.reader.u8()? {
+            Self::NULL => {}
+            Self::REFERENCE => {
+                self.handle()?;
+            }
+            Self::STRING => {
+                self.reader.utf()?;
+                self.handles.push(None);
+            }
+            Self::LONG_STRING => {
+                let length = self.reader.u64()? as usize;
+                self.reader.take(length)?;
+                self.handles.push(None);
+            }
+            Self::OBJECT => {
+                let class = self.class()?.ok_or_else(|| error("object without a class"))?;
+                self.handles.push(None);
+                let mut hierarchy = vec![class];
+                while let Some(parent) = hierarchy.last().and_then(|class| class.parent.clone()) {
+                    hierarchy.push(parent);
+                }
+                for class in hierarchy.iter().rev() {
+                    if class.flags & Self::EXTERNALIZABLE != 0 {
+                        return Err(error(format!("{} is externalizable", class.name)));
+                    }
+                    for &kind in &class.fields {
+                        self.value(kind)?;
+                    }
+                    if class.flags & Self::WRITE_METHOD != 0 {
+                        self.annotation()?;
+                    }
+                }
+            }
+            Self::ARRAY => {
+                let class = self.class()?.ok_or_else(|| error("array without a class"))?;
+                self.handles.push(None);
+                let kind = class.name.as_bytes().get(1).copied().unwrap_or_default();
+                for _ in 0..self.reader.u32()? {
+                    self.value(kind)?;
+                }
+            }
+            Self::ENUM => {
+                self.class()?;
+                self.handles.push(None);
+                self.object()?;
+            }
+            Self::CLASS => {
+                self.class()?;
+                self.handles.push(None);
+            }
+            Self::BLOCK_DATA => {
+                let length = self.reader.u8()? as usize;
+                self.reader.take(length)?;
+            }
+            Self::BLOCK_DATA_LONG => {
+                let length = self.reader.u32()? as usize;
+                self.reader.take(length)?;
+            }
+            Self::RESET => self.handles.clear(),
+            tag => return Err(error(format!("unsupported serialized content 0x{:02x}", tag))),
+        }
+        Ok(())
+    }
+}
+
+fn digest(password: &str, store: &[u8]) -> Vec<u8> {
+    let mut sha1 = Sha1::new();
+    for unit in password.encode_utf16() {
+        sha1.update(unit.to_be_bytes());
+    }
+    sha1.update(WHITENER);
+    sha1.update(store);
+    sha1.finalize().to_vec()
+}
+
+/// The certificates in a JKS or JCEKS keystore, entry by entry with each private key's chain
+/// leaf first, and an entry per alias. Keys stay encrypted; the password only checks integrity,
+/// which is skipped with a warning when the prompt gets no answer.
+pub fn decode(
+    data: &[u8],
+    password: &mut dyn FnMut() -> Result<String, InputError>,
+) -> Result<(Vec<Certificate>, Vec<Entry>)> {
+    let mut reader = Reader { data, position: 0 };
+    let magic = reader.take(4)?;
+    let version = reader.u32()?;
+    if version != 1 && version != 2 {
+        return Err(error(format!("unsupported version {}", version)).into());
+    }
+
+    let mut certificates = Vec::new();
+    let mut entries = Vec::new();
+    for _ in 0..reader.u32()? {
+        let tag = reader.u32()?;
+        let alias = reader.utf()?;
+        reader.u64()?;
+        let start = certificates.len();
+        let (kind, key_algorithm) = match tag {
+            PRIVATE_KEY => {
+                reader.bytes()?;
+                for _ in 0..reader.u32()? {
+                    certificates.push(reader.certificate(version)?);
+                }
+                let algorithm = certificates.get(start).map(|leaf| leaf.public_key.algorithm.clone());
+                (EntryKind::PrivateKey, algorithm)
+            }
+            TRUSTED_CERTIFICATE => {
+                certificates.push(reader.certificate(version)?);
+                (EntryKind::Certificate, None)
+            }
+            SECRET_KEY if magic == JCEKS_MAGIC => {
+                Serialized::skip(&mut reader)?;
+                (EntryKind::SecretKey, None)
+            }
+            tag => return Err(error(format!("unsupported entry type {} for {}", tag, alias)).into()),
+        };
+        entries.push(Entry::new(
+            Some(alias),
+            kind,
+            key_algorithm,
+            (start..certificates.len()).collect(),
+        ));
+    }
+
+    let store = &data[..reader.position];
+    let expected = reader.take(20)?;
+    match password() {
+        Ok(password) if digest(&password, store) != expected => return Err(InputError::WrongPassword.into()),
+        Ok(_) => {}
+        Err(InputError::Password { input, reason }) => {
+            warn!("integrity of {} not verified: {}", input, reason)
+        }
+        Err(e) => return Err(e.into()),
+    }
+    if certificates.is_empty() {
+        return Err(ParseError::NoCertificate.into());
+    }
+    keystore::describe(&mut entries, &certificates);
+    Ok((certificates, entries))
+}
@@ -0,0 +1,137 @@
+use crate::backend::Backend;
+use crate::cert::{format_time, Certificate};
+use crate::error::InputError;
+use serde::Serialize;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use time::OffsetDateTime;
+
+/// Where the password for an encrypted input, such as a PKCS#12 file, comes from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Password {
+    /// Ask through the backend, which prompts on the terminal.
+    #[default]
+    Prompt,
+    /// The value of this environment variable.
+    Env(String),
+    /// The first line of this file.
+    File(PathBuf),
+}
+
+impl Password {
+    pub fn resolve(&self, input: &str, backend: &dyn Backend) -> Result<String, InputError> {
+        let error = |reason: String| InputError::Password {
+            input: input.to_string(),
+            reason,
+        };
+        match self {
+            // pressing Enter at the prompt means not knowing it, which Java keystores can live with
+            Password::Prompt => match backend.password(input)? {
+                Some(password) if password.is_empty() => Err(error("no password was entered".into())),
+                Some(password) => Ok(password),
+                None => Err(error(
+                    "there is no terminal to ask on; use --password-env or --password-file".into(),
+                )),
+            },
+            Password::Env(name) => env::var(name).map_err(|_| InputError::PasswordUnset {
+                input: input.to_string(),
+                name: name.clone(),
+            }),
+            Password::File(path) => {
+                let data = fs::read_to_string(path).map_err(InputError::read(path))?;
+                Ok(data.lines().next().unwrap_or_default().to_string())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+    PrivateKey,
+    Certificate,
+    SecretKey,
+}
+
+impl fmt::Display for EntryKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EntryKind::PrivateKey => write!(f, "private key"),
+            EntryKind::Certificate => write!(f, "certificate"),
+            EntryKind::SecretKey => write!(f, "secret key"),
+        }
+    }
+}
+
+/// One entry of a keystore, described without its key material. `certificates` are positions
+/// in [`Inspection::certificates`](crate::Inspection::certificates), leaf first, and the
+/// subject, expiry and fingerprint are those of the first of them.
+#[derive(Debug, Clone, Serialize)]
+pub struct Entry {
+    pub alias: Option<String>,
+    pub kind: EntryKind,
+    pub key_algorithm: Option<String>,
+    pub certificates: Vec<usize>,
+    pub subject: Option<String>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub not_after: Option<OffsetDateTime>,
+    pub fingerprint: Option<String>,
+}
+
+impl Entry {
+    pub(crate) fn new(
+        alias: Option<String>,
+        kind: EntryKind,
+        key_algorithm: Option<String>,
+        certificates: Vec<usize>,
+    ) -> Entry {
+        Entry {
+            alias,
+            kind,
+            key_algorithm,
+            certificates,
+            subject: None,
+            not_after: None,
+            fingerprint: None,
+        }
+    }
+}
+
+/// Fills in each entry's subject, expiry and fingerprint once `certificates` is in its final order.
+pub(crate) fn describe(entries: &mut [Entry], certificates: &[Certificate]) {
+    for entry in entries {
+        if let Some(certificate) = entry
+            .certificates
+            .first()
+            .and_then(|&position| certificates.get(position))
+        {
+            entry.subject = Some(certificate.subject.clone());
+            entry.not_after = Some(certificate.not_after);
+            entry.fingerprint = Some(certificate.fingerprint.clone());
+        }
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.alias.as_deref().unwrap_or("(no alias)"), self.kind)?;
+        if let Some(algorithm) = &self.key_algorithm {
+            write!(f, " ({})", algorithm)?;
+        }
+        let positions: Vec<String> = self.certificates.iter().map(usize::to_string).collect();
+        match self.kind {
+            _ if positions.is_empty() => {}
+            EntryKind::Certificate => write!(f, " {}", positions.join(", "))?,
+            _ => write!(f, ", chain {}", positions.join(" → "))?,
+        }
+        if let (Some(subject), Some(not_after)) = (&self.subject, self.not_after) {
+            write!(f, "\n    {}, expires {}", subject, format_time(not_after))?;
+        }
+        if let Some(fingerprint) = &self.fingerprint {
+            write!(f, "\n    {}", fingerprint)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,43 @@
+//! Fetch, parse, inspect, compare and verify X.509 certificates.
+//!
+//! The `ssl` command line tool is a thin wrapper over this crate: every subcommand is one of
+//! the functions below, and every result type serialises to the same schema as `--output json`.
+//!
+//! ```no_run
+//! use ssl::{inspect, Options};
+//!
+//! let inspection = inspect("example.com", &Options::default())?;
+//! let leaf = &inspection.certificates[0];
+//! println!("{} expires {}", leaf.subject, leaf.not_after);
+//! # Ok::<(), ssl::Error>(())
+//! ```
+
+pub mod backend;
+pub mod cert;
+pub mod chain;
+pub mod compare;
+pub mod error;
+pub mod format;
+pub mod hostname;
+pub mod inspect;
+pub mod jks;
+pub mod keystore;
+pub mod openssl;
+pub mod pkcs12;
+pub mod pkcs7;
+pub mod sans;
+pub mod target;
+pub mod tls;
+pub mod validity;
+pub mod verify;
+
+pub use cert::Certificate;
+pub use compare::{compare, Comparison, Difference};
+pub use error::{Error, Result};
+pub use hostname::{matches, NameMatch};
+pub use inspect::{fetch, inspect, InputKind, Inspection, Options, Selection};
+pub use sans::{sans, Sans};
+pub use target::{FetchOptions, Target};
+pub use tls::PeerChain;
+pub use validity::{validity, Validity};
+pub use verify::{verify, TrustStore, Verification};
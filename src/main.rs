@@ -1,33 +1,136 @@
 #![cfg_attr(debug_assertions, allow(unused_imports, unused_variables, unused_mut, dead_code))]
 
-use clap::{Parser, Subcommand};
-use eyre::{eyre, Result};
-use regex::Regex;
-use std::fs;
-use std::io::{self, Read, Write};
-use std::path::Path;
-use std::process::{Command, Stdio};
+use clap::{Parser, Subcommand, ValueEnum};
+use log::{Level, LevelFilter};
+use serde::Serialize;
+use std::env;
+use std::fmt;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+
+mod status;
+
+use ssl::backend::{Native, Openssl};
+use ssl::cert::EXPIRY_WARNING_DAYS;
+use ssl::error::InputError;
+use ssl::keystore::Password;
+use ssl::target::Resolve;
+use ssl::{
+    compare, inspect, matches, sans, validity, verify, Error, FetchOptions, InputKind, Options, Result, Selection,
+    TrustStore,
+};
+use status::Status;
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct Cli {
     #[clap(subcommand)]
     command: Commands,
+
+    /// SNI name to send instead of the target host
+    #[clap(long, global = true)]
+    servername: Option<String>,
+
+    /// Connect to this address (host or host:port) instead of resolving the target
+    #[clap(long, global = true)]
+    connect: Option<String>,
+
+    /// Pin a target to an address, curl-style; may be repeated
+    #[clap(long, global = true, value_name = "HOST:PORT:ADDR")]
+    resolve: Vec<Resolve>,
+
+    /// Operate on the whole presented chain instead of just the leaf
+    #[clap(long, global = true)]
+    chain: bool,
+
+    /// Operate on every certificate of a bundle, in order
+    #[clap(long, global = true, conflicts_with = "chain")]
+    all: bool,
+
+    /// Operate on the certificate at this position of a bundle, counting from 0
+    #[clap(long, global = true, value_name = "N", conflicts_with_all = ["all", "chain"])]
+    index: Option<usize>,
+
+    /// Treat inputs as files, even when they look like hosts
+    #[clap(long, global = true, conflicts_with_all = ["stdin", "host"])]
+    file: bool,
+
+    /// Read the certificate from stdin, as an input of "-" does
+    #[clap(long, global = true, conflicts_with = "host")]
+    stdin: bool,
+
+    /// Treat inputs as host[:port] targets, even when a file of that name exists
+    #[clap(long, global = true)]
+    host: bool,
+
+    /// Read the password for PKCS#12 and Java keystore inputs from this environment variable instead of prompting
+    #[clap(long, global = true, value_name = "VAR")]
+    password_env: Option<String>,
+
+    /// Read the password for PKCS#12 and Java keystore inputs from the first line of this file instead of prompting
+    #[clap(long, global = true, value_name = "PATH", conflicts_with = "password_env")]
+    password_file: Option<PathBuf>,
+
+    /// Fall back to the subject CN when a certificate has no DNS SANs
+    #[clap(long, global = true)]
+    legacy_cn: bool,
+
+    /// Output format
+    #[clap(long, global = true, value_enum, default_value_t = Output::Text)]
+    output: Output,
+
+    /// How to perform TLS handshakes
+    #[clap(long, global = true, value_enum, default_value_t = BackendKind::Native)]
+    backend: BackendKind,
+
+    /// Log more about what is going on (-v for info, -vv for debug, -vvv for trace)
+    #[clap(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Only log errors
+    #[clap(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Output {
+    Text,
+    Json,
+    Yaml,
+    Toml,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum BackendKind {
+    /// In-process rustls
+    Native,
+    /// The openssl s_client binary on PATH
+    Openssl,
 }
 
 #[derive(Subcommand, Debug)]
 enum Commands {
     Inspect {
-        #[clap(value_parser)]
+        #[clap(value_parser, default_value = "-")]
         domain: String,
+
+        /// Print the full text dump of each certificate instead of the summary
+        #[clap(long)]
+        raw: bool,
     },
     Sans {
-        #[clap(value_parser)]
+        #[clap(value_parser, default_value = "-")]
         domain: String,
     },
     Validity {
-        #[clap(value_parser)]
+        #[clap(value_parser, default_value = "-")]
         domain: String,
+
+        /// Exit with the expiring-soon status when fewer days than this remain
+        #[clap(long, value_name = "DAYS", default_value_t = EXPIRY_WARNING_DAYS)]
+        warn_days: i64,
     },
     Compare {
         #[clap(value_parser)]
@@ -35,177 +138,269 @@ enum Commands {
         #[clap(value_parser)]
         domain2: String,
     },
-}
+    /// Check whether the leaf is valid for a hostname or IP address, explaining why
+    Matches {
+        #[clap(value_parser)]
+        domain: String,
+        #[clap(value_parser)]
+        hostname: String,
+    },
+    /// Validate the path from the leaf to a trusted root
+    Verify {
+        #[clap(value_parser, default_value = "-")]
+        domain: String,
 
-#[derive(Debug)]
-enum InputType {
-    Domain(String),
-    File(String),
-    Stdin(String),
-}
+        /// PEM bundle of trusted CAs to use instead of the system store
+        #[clap(long)]
+        ca_file: Option<PathBuf>,
 
-fn is_stdin_empty() -> Result<bool, io::Error> {
-    let mut buffer = [0; 1];
-    let stdin = io::stdin();
-    let mut handle = stdin.lock();
+        /// Directory of PEM CA certificates to use instead of the system store
+        #[clap(long)]
+        ca_dir: Option<PathBuf>,
 
-    match handle.read(&mut buffer) {
-        Ok(0) | Err(_) => Ok(true),
-        Ok(_) => Ok(false),
-    }
+        /// Name the leaf must be valid for (defaults to the SNI name for hosts)
+        #[clap(long)]
+        hostname: Option<String>,
+    },
 }
 
-fn input_type(input: &str) -> Result<InputType> {
-    if Path::new(input).exists() && fs::metadata(input)?.is_file() {
-        Ok(InputType::File(input.to_string()))
-    } else if input.contains('.') && !input.contains('/') {
-        Ok(InputType::Domain(input.to_string()))
-    } else if !is_stdin_empty()? {
-        let mut buffer = String::new();
-        io::stdin().read_to_string(&mut buffer)?;
-        Ok(InputType::Stdin(buffer))
-    } else {
-        Err(eyre!("Input does not match any expected type"))
+// a single result prints as-is; chain results are labelled with their depth, bundles with
+// their position
+fn render<T: fmt::Display>(results: &[T], chain: bool) -> String {
+    if results.len() == 1 {
+        return results[0].to_string();
     }
+    let label = if chain { "depth" } else { "certificate" };
+    results
+        .iter()
+        .enumerate()
+        .map(|(index, result)| format!("{} {}:\n{}", label, index, result))
+        .collect::<Vec<_>>()
+        .join("\n\n")
 }
 
-fn fetch_certificate_from_domain(domain: &str) -> Result<String> {
-    let mut cmd = Command::new("openssl");
-    cmd.args(&[
-        "s_client",
-        "-connect",
-        &format!("{}:443", domain),
-        "-servername",
-        domain,
-        //"-showcerts",
-    ]);
-    execute_command(cmd, None)
+// text is rendered only when asked for; structured formats serialise the result itself
+fn emit<T: Serialize + ?Sized>(output: Output, text: impl FnOnce() -> String, result: &T) -> Result<()> {
+    let rendered = match output {
+        Output::Text => text() + "\n",
+        Output::Json => serde_json::to_string_pretty(result).map_err(|e| output_error("JSON", e))? + "\n",
+        Output::Yaml => serde_yaml_ng::to_string(result).map_err(|e| output_error("YAML", e))?,
+        Output::Toml => to_toml(result)?,
+    };
+    write_out(&mut io::stdout().lock(), &rendered)
 }
 
-fn execute_command(mut cmd: Command, input_data: Option<&str>) -> Result<String> {
-    println!("execute_command: cmd: {:?}", cmd);
-    if let Some(data) = input_data {
-        cmd.stdin(Stdio::piped());
+// a reader that stops early, like `head`, closes the pipe; the command's own status still stands
+fn write_out(out: &mut impl Write, rendered: &str) -> Result<()> {
+    match out.write_all(rendered.as_bytes()).and_then(|()| out.flush()) {
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => Err(InputError::Write {
+            path: PathBuf::from("stdout"),
+            source: e,
+        }
+        .into()),
+        _ => Ok(()),
     }
-    cmd.stderr(Stdio::piped());
-
-    let output = if let Some(data) = input_data {
-        //println!("execute_command: input_data: {}", data);
-        let mut child = cmd.spawn()?;
-        if let Some(ref mut stdin) = child.stdin.take() {
-            stdin.write_all(data.as_bytes())?;
-        } else {
-            return Err(eyre!("Failed to open stdin"));
+}
+
+// a TOML document has to be a table, so chain arrays are wrapped under a `results` key
+fn to_toml<T: Serialize + ?Sized>(result: &T) -> Result<String> {
+    let value = match toml::Value::try_from(result).map_err(|e| output_error("TOML", e))? {
+        toml::Value::Array(results) => {
+            let mut table = toml::Table::new();
+            table.insert("results".to_string(), toml::Value::Array(results));
+            toml::Value::Table(table)
         }
-        child.wait_with_output()?
-    } else {
-        println!("execute_command: no input_data");
-        cmd.output()?
+        value => value,
     };
+    toml::to_string_pretty(&value).map_err(|e| output_error("TOML", e))
+}
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(eyre!(
-            "Command execution failed with status: {:?}, stderr: {}",
-            output.status,
-            stderr
-        ));
+fn output_error(format: &'static str, error: impl fmt::Display) -> Error {
+    Error::Output {
+        format,
+        reason: error.to_string(),
     }
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-
-    if stdout.is_empty() && stderr.is_empty() {
-        return Err(eyre!("Both stdout and stderr are empty"));
+// per-certificate results are a single object for one certificate and an array with --all or --chain
+fn emit_all<T: fmt::Display + Serialize>(output: Output, results: &[T], options: &Options) -> Result<()> {
+    if options.selects_all() {
+        emit(output, || render(results, options.chain), results)
+    } else {
+        emit(output, || render(results, options.chain), &results[0])
     }
-
-    println!("execute_command: stdout: {}", stdout);
-    println!("execute_command: stderr: {}", stderr);
-
-    Ok(stdout)
 }
 
-fn inspect(input: &str) -> Result<String> {
-    let result = match input_type(input)? {
-        InputType::Domain(domain) => {
-            let certificate_data = fetch_certificate_from_domain(&domain)?;
-            let mut x509_cmd = Command::new("openssl");
-            x509_cmd
-                .args(&["x509", "-noout", "-text"])
-                .stdin(Stdio::piped())
-                .stdout(Stdio::piped());
-            execute_command(x509_cmd, Some(&certificate_data))
+/// Runs the selected command and returns the status its outcome maps to.
+fn run(cli: Cli) -> Result<Status> {
+    let output = cli.output;
+    let options = Options {
+        fetch: FetchOptions {
+            servername: cli.servername,
+            connect: cli.connect,
+            resolve: cli.resolve,
+        },
+        input: match (cli.file, cli.stdin, cli.host) {
+            (true, _, _) => InputKind::File,
+            (_, true, _) => InputKind::Stdin,
+            (_, _, true) => InputKind::Host,
+            _ => InputKind::Auto,
+        },
+        chain: cli.chain,
+        select: match (cli.all, cli.index) {
+            (true, _) => Selection::All,
+            (_, Some(index)) => Selection::Index(index),
+            _ => Selection::Leaf,
+        },
+        password: match (cli.password_env, cli.password_file) {
+            (Some(name), _) => Password::Env(name),
+            (_, Some(path)) => Password::File(path),
+            _ => Password::Prompt,
+        },
+        legacy_cn: cli.legacy_cn,
+        backend: match cli.backend {
+            BackendKind::Native => Arc::new(Native),
+            BackendKind::Openssl => Arc::new(Openssl),
+        },
+    };
+    match cli.command {
+        Commands::Inspect { domain, raw } => {
+            let result = inspect(&domain, &options)?;
+            emit(output, || result.render(raw), &result)?;
+            Ok(Status::Ok)
+        }
+        Commands::Sans { domain } => {
+            emit_all(output, &sans(&domain, &options)?, &options)?;
+            Ok(Status::Ok)
+        }
+        Commands::Validity { domain, warn_days } => {
+            let results = validity(&domain, &options)?;
+            emit_all(output, &results, &options)?;
+            Ok(Status::validity(&results, warn_days))
         }
-        InputType::File(file_path) => {
-            let mut x509_cmd = Command::new("openssl");
-            x509_cmd
-                .args(&["x509", "-in", &file_path, "-text", "-noout"])
-                .stdout(Stdio::piped());
-            execute_command(x509_cmd, None)
+        Commands::Compare { domain1, domain2 } => {
+            let result = compare(&domain1, &domain2, &options)?;
+            emit(output, || result.to_string(), &result)?;
+            Ok(if result.matches() { Status::Ok } else { Status::Mismatch })
         }
-        InputType::Stdin(stdin_content) => {
-            //let x509_cmd = create_x509_command();
-            let mut x509_cmd = Command::new("openssl");
-            x509_cmd
-                .args(&["x509", "-noout", "-text"])
-                .stdin(Stdio::piped())
-                .stdout(Stdio::piped());
-            execute_command(x509_cmd, Some(&stdin_content))
+        Commands::Matches { domain, hostname } => {
+            let result = matches(&domain, &hostname, &options)?;
+            emit(output, || result.to_string(), &result)?;
+            Ok(if result.is_match() {
+                Status::Ok
+            } else {
+                Status::Verification
+            })
         }
+        Commands::Verify {
+            domain,
+            ca_file,
+            ca_dir,
+            hostname,
+        } => {
+            let store = TrustStore::load(ca_file.as_deref(), ca_dir.as_deref())?;
+            let result = verify(&domain, &store, hostname.as_deref(), &options)?;
+            emit(output, || result.to_string(), &result)?;
+            Ok(if result.is_valid() {
+                Status::Ok
+            } else {
+                Status::Verification
+            })
+        }
+    }
+}
+
+// diagnostics go to stderr so stdout only ever carries results; SSL_LOG takes env_logger
+// filter syntax (e.g. `SSL_LOG=debug` or `SSL_LOG=ssl::tls=trace`) and overrides the flags
+fn init_logging(verbose: u8, quiet: bool) {
+    let level = match (quiet, verbose) {
+        (true, _) => LevelFilter::Error,
+        (false, 0) => LevelFilter::Warn,
+        (false, 1) => LevelFilter::Info,
+        (false, 2) => LevelFilter::Debug,
+        (false, _) => LevelFilter::Trace,
     };
-    println!("inspect: result: {:?}", result);
-    result
+    env_logger::Builder::new()
+        .filter_level(level)
+        .parse_env("SSL_LOG")
+        .format(|buf, record| {
+            let level = match record.level() {
+                Level::Warn => "warning".to_string(),
+                level => level.as_str().to_lowercase(),
+            };
+            writeln!(buf, "{}: {}", level, record.args())
+        })
+        .init();
+}
+
+fn main() -> ExitCode {
+    // colored already honours NO_COLOR and CLICOLOR_FORCE; it does not notice pipes
+    if !io::stdout().is_terminal() && env::var_os("CLICOLOR_FORCE").is_none() {
+        colored::control::set_override(false);
+    }
+    let cli = Cli::parse();
+    init_logging(cli.verbose, cli.quiet);
+    match run(cli) {
+        Ok(status) => status.into(),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            Status::from(&e).into()
+        }
+    }
 }
 
-fn sans(domain: &str) -> Result<String> {
-    let inspect_output = inspect(domain)?;
-    println!("Full output from inspect:\n{}", inspect_output);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let mut count = 0;
-    let lines: Vec<&str> = inspect_output.split('\n').collect();
-    for line in lines.iter() {
-        count += 1;
-        println!("{}: {}", count, line);
+    #[derive(Serialize)]
+    struct Sample {
+        subject: &'static str,
+        days_remaining: i64,
     }
 
-    Ok(inspect_output)
-}
+    const LEAF: Sample = Sample {
+        subject: "CN=leaf.test",
+        days_remaining: 90,
+    };
 
-fn validity(domain: &str) -> Result<String> {
-    let inspect_output = inspect(domain)?;
-    // Extract validity information from the inspect_output
-    // Similar to the Sans function, parse the output to find the validity dates
-    // Return the validity information or an error if something goes wrong
-    todo!()
-}
+    struct Failing(io::ErrorKind);
 
-fn compare(domain1: &str, domain2: &str) -> Result<String> {
-    let inspect_output1 = inspect(domain1)?;
-    let inspect_output2 = inspect(domain2)?;
-    // Compare the outputs
-    // You can decide how to compare (e.g., direct string comparison, structured parsing, etc.)
-    // Return a message indicating whether they match or not
-    todo!()
-}
+    impl Write for Failing {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(self.0.into())
+        }
 
-fn main() {
-    let cli = Cli::parse();
-    match cli.command {
-        Commands::Inspect { domain } => match inspect(&domain) {
-            Ok(result) => println!("{}", result),
-            Err(e) => eprintln!("Error: {}", e),
-        },
-        Commands::Sans { domain } => match sans(&domain) {
-            Ok(result) => println!("{}", result),
-            Err(e) => eprintln!("Error: {}", e),
-        },
-        Commands::Validity { domain } => match validity(&domain) {
-            Ok(result) => println!("{}", result),
-            Err(e) => eprintln!("Error: {}", e),
-        },
-        Commands::Compare { domain1, domain2 } => match compare(&domain1, &domain2) {
-            Ok(result) => println!("{}", result),
-            Err(e) => eprintln!("Error: {}", e),
-        },
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn closed_pipes_end_output_quietly() {
+        assert!(write_out(&mut Failing(io::ErrorKind::BrokenPipe), "result\n").is_ok());
+        let result = write_out(&mut Failing(io::ErrorKind::StorageFull), "result\n");
+        assert!(matches!(result, Err(Error::Input(InputError::Write { .. }))));
+        let mut buffer = Vec::new();
+        write_out(&mut buffer, "result\n").unwrap();
+        assert_eq!(buffer, b"result\n");
+    }
+
+    #[test]
+    fn toml_keeps_single_results_as_the_document() {
+        assert_eq!(
+            to_toml(&LEAF).unwrap(),
+            "subject = \"CN=leaf.test\"\ndays_remaining = 90\n"
+        );
+    }
+
+    #[test]
+    fn toml_wraps_result_arrays_under_results() {
+        let toml = to_toml(&[LEAF, LEAF]).unwrap();
+        let parsed: toml::Table = toml.parse().unwrap();
+        let results = parsed["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[1]["subject"].as_str(), Some("CN=leaf.test"));
+        assert!(toml.starts_with("[[results]]\n"));
     }
 }
@@ -0,0 +1,217 @@
+use crate::cert::Certificate;
+use crate::error::{NetworkError, Result, TlsHandshakeError};
+use crate::tls::{PeerChain, TIMEOUT};
+use log::{debug, info};
+use std::io::{self, Read};
+use std::net::IpAddr;
+use std::process::{Child, Command, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+// what `openssl ciphers` calls the TLS 1.2 suites rustls offers, in rustls's own naming
+const CIPHER_SUITES: [(&str, &str); 6] = [
+    (
+        "ECDHE-ECDSA-AES256-GCM-SHA384",
+        "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384",
+    ),
+    (
+        "ECDHE-ECDSA-AES128-GCM-SHA256",
+        "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256",
+    ),
+    (
+        "ECDHE-ECDSA-CHACHA20-POLY1305",
+        "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256",
+    ),
+    ("ECDHE-RSA-AES256-GCM-SHA384", "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384"),
+    ("ECDHE-RSA-AES128-GCM-SHA256", "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256"),
+    (
+        "ECDHE-RSA-CHACHA20-POLY1305",
+        "TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256",
+    ),
+];
+
+fn execute_command(command: &mut Command) -> Result<(bool, String, String), TlsHandshakeError> {
+    debug!("running {:?}", command);
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|source| TlsHandshakeError::Openssl { source })?;
+    // -showcerts of a long chain can outgrow a pipe buffer, so both are read while openssl runs
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+    let success = wait(&mut child).map_err(|source| TlsHandshakeError::Openssl { source })?;
+    Ok((
+        success,
+        stdout.join().unwrap_or_default(),
+        stderr.join().unwrap_or_default(),
+    ))
+}
+
+fn drain(pipe: Option<impl Read + Send + 'static>) -> JoinHandle<String> {
+    thread::spawn(move || {
+        let mut text = String::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_string(&mut text);
+        }
+        text
+    })
+}
+
+// s_client has no timeout of its own
+fn wait(child: &mut Child) -> io::Result<bool> {
+    let deadline = Instant::now() + TIMEOUT;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status.success());
+        }
+        if Instant::now() > deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "timed out"));
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+/// `TLSv1.3` → `TLSv1_3`, matching what the native backend reports.
+fn protocol(name: &str) -> String {
+    name.replace('.', "_")
+}
+
+fn cipher_suite(name: &str, protocol: &str) -> String {
+    if protocol == "TLSv1_3" {
+        if let Some(rest) = name.strip_prefix("TLS_") {
+            return format!("TLS13_{}", rest);
+        }
+    }
+    CIPHER_SUITES
+        .iter()
+        .find(|(openssl, _)| *openssl == name)
+        .map(|(_, rustls)| rustls.to_string())
+        .unwrap_or_else(|| name.to_string())
+}
+
+fn failure(address: &str, server_name: &str, stderr: &str) -> crate::error::Error {
+    // id:error:code:library:function:reason:file:line[:detail], where "system lib" defers to the detail
+    let reason = stderr
+        .lines()
+        .find(|line| line.contains(":error:"))
+        .and_then(|line| {
+            let fields: Vec<&str> = line.split(':').collect();
+            match fields.get(5) {
+                Some(&"system lib") => fields.last().copied(),
+                reason => reason.copied(),
+            }
+        })
+        .unwrap_or_else(|| stderr.lines().last().unwrap_or("openssl failed"))
+        .to_string();
+    if stderr.contains("BIO_lookup") {
+        NetworkError::Resolve {
+            address: address.to_string(),
+            source: io::Error::other(reason),
+        }
+        .into()
+    } else if stderr.contains("Connection refused") || stderr.contains("connect error") {
+        let port = address.rsplit(':').next().and_then(|port| port.parse().ok());
+        NetworkError::Connect {
+            address: address.to_string(),
+            port: port.unwrap_or_default(),
+            source: io::Error::new(io::ErrorKind::ConnectionRefused, reason),
+        }
+        .into()
+    } else {
+        TlsHandshakeError::Handshake {
+            address: address.to_string(),
+            server_name: server_name.to_string(),
+            source: io::Error::other(reason),
+        }
+        .into()
+    }
+}
+
+/// Same as [`crate::tls::fetch_peer_chain`], but by running `openssl s_client -showcerts`.
+pub fn fetch_peer_chain(server_name: &str, address: &str) -> Result<PeerChain> {
+    let mut command = Command::new("openssl");
+    command.args(["s_client", "-connect", address, "-showcerts", "-alpn", "h2,http/1.1"]);
+    // rustls never sends an IP address as SNI, so neither do we
+    match server_name.parse::<IpAddr>() {
+        Ok(_) => command.arg("-noservername"),
+        Err(_) => command.args(["-servername", server_name]),
+    };
+    let (success, stdout, stderr) = execute_command(&mut command)?;
+    if !success {
+        return Err(failure(address, server_name, &stderr));
+    }
+
+    let certificates: Vec<Vec<u8>> = Certificate::all_from_pem(stdout.as_bytes())
+        .map(|certificates| certificates.into_iter().map(|certificate| certificate.der).collect())
+        .unwrap_or_default();
+    if certificates.is_empty() {
+        return Err(TlsHandshakeError::NoCertificate {
+            address: address.to_string(),
+        }
+        .into());
+    }
+
+    let (protocol, cipher_suite) = stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("New, "))
+        .and_then(|line| line.split_once(", Cipher is "))
+        .map(|(version, cipher)| {
+            let version = protocol(version);
+            let cipher = cipher_suite(cipher, &version);
+            (version, cipher)
+        })
+        .unwrap_or_default();
+    let alpn = stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("ALPN protocol: "))
+        .map(str::to_string);
+
+    info!(
+        "{} presented {} certificate(s) over {} ({}) to openssl",
+        address,
+        certificates.len(),
+        protocol,
+        cipher_suite
+    );
+    Ok(PeerChain {
+        certificates,
+        protocol,
+        cipher_suite,
+        alpn,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_names_to_rustls_spelling() {
+        assert_eq!(protocol("TLSv1.2"), "TLSv1_2");
+        assert_eq!(
+            cipher_suite("TLS_AES_256_GCM_SHA384", "TLSv1_3"),
+            "TLS13_AES_256_GCM_SHA384"
+        );
+        assert_eq!(
+            cipher_suite("ECDHE-RSA-AES128-GCM-SHA256", "TLSv1_2"),
+            "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256"
+        );
+        assert_eq!(cipher_suite("AES128-SHA", "TLSv1_2"), "AES128-SHA");
+    }
+
+    #[test]
+    fn reads_output_larger_than_a_pipe_buffer() {
+        let mut command = Command::new("sh");
+        command.args(["-c", "head -c 300000 /dev/zero | tr '\\0' a; echo done >&2"]);
+        let started = Instant::now();
+        let (success, stdout, stderr) = execute_command(&mut command).unwrap();
+        assert!(success);
+        assert_eq!(stdout.len(), 300000);
+        assert_eq!(stderr, "done\n");
+        assert!(started.elapsed() < TIMEOUT);
+    }
+}
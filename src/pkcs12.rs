@@ -0,0 +1,101 @@
+use crate::cert::{oid_name, Certificate};
+use crate::error::{InputError, ParseError, Result};
+use crate::keystore::{self, Entry, EntryKind};
+use log::debug;
+use p12_keystore::error::Error as Pkcs12Error;
+use p12_keystore::{CertificateBag, Pkcs12Archive, PrivateKeyBag};
+use std::str::FromStr;
+use x509_parser::der_parser::oid::Oid;
+
+fn parse_error(error: Pkcs12Error) -> ParseError {
+    ParseError::Pkcs12 {
+        reason: error.to_string(),
+    }
+}
+
+// exports without a password are common, so that is tried before asking for one
+fn open(data: &[u8], password: &mut dyn FnMut() -> Result<String, InputError>) -> Result<Pkcs12Archive> {
+    match Pkcs12Archive::from_pkcs12(data, "") {
+        Err(Pkcs12Error::MacError(_)) => {}
+        result => return Ok(result.map_err(parse_error)?),
+    }
+    debug!("PKCS#12 input is password protected");
+    match Pkcs12Archive::from_pkcs12(data, &password()?) {
+        Err(Pkcs12Error::MacError(_)) => Err(InputError::WrongPassword.into()),
+        result => Ok(result.map_err(parse_error)?),
+    }
+}
+
+fn key_algorithm(key: &PrivateKeyBag) -> String {
+    let oid = key.key.oid().to_string();
+    Oid::from_str(&oid).map(|oid| oid_name(&oid)).unwrap_or(oid)
+}
+
+fn leaf(key: &PrivateKeyBag, bags: &[CertificateBag]) -> Option<usize> {
+    let id = key.local_key_id.as_ref()?;
+    bags.iter()
+        .position(|bag| bag.local_key_id.as_deref() == Some(id.as_ref()))
+}
+
+/// The certificates in a PKCS#12 archive, each key's leaf followed by its issuers, and an entry
+/// per private key, secret key and certificate that is not a key's leaf.
+pub fn decode(
+    data: &[u8],
+    password: &mut dyn FnMut() -> Result<String, InputError>,
+) -> Result<(Vec<Certificate>, Vec<Entry>)> {
+    let archive = open(data, password)?;
+    let parsed = archive
+        .certs
+        .iter()
+        .map(|bag| Certificate::from_der(bag.cert.as_der()))
+        .collect::<Result<Vec<_>, ParseError>>()?;
+    if parsed.is_empty() {
+        return Err(ParseError::NoCertificate.into());
+    }
+
+    // bag order is arbitrary, so chains are rebuilt by following issuer names from each leaf
+    let mut order: Vec<usize> = Vec::new();
+    let mut entries = Vec::new();
+    let mut leaves = Vec::new();
+    for key in &archive.keys {
+        let start = order.len();
+        let mut next = leaf(key, &archive.certs);
+        leaves.extend(next);
+        while let Some(bag) = next.filter(|bag| !order.contains(bag)) {
+            order.push(bag);
+            next = (0..parsed.len())
+                .find(|&issuer| !order.contains(&issuer) && parsed[issuer].subject == parsed[bag].issuer);
+        }
+        entries.push(Entry::new(
+            key.friendly_name.clone(),
+            EntryKind::PrivateKey,
+            Some(key_algorithm(key)),
+            (start..order.len()).collect(),
+        ));
+    }
+    let unchained: Vec<usize> = (0..parsed.len()).filter(|bag| !order.contains(bag)).collect();
+    order.extend(unchained);
+    for (bag, certificate) in archive.certs.iter().enumerate() {
+        if !leaves.contains(&bag) {
+            entries.push(Entry::new(
+                certificate.friendly_name.clone(),
+                EntryKind::Certificate,
+                None,
+                order.iter().position(|&position| position == bag).into_iter().collect(),
+            ));
+        }
+    }
+    entries.extend(archive.secrets.iter().map(|secret| {
+        Entry::new(
+            secret.friendly_name.clone(),
+            EntryKind::SecretKey,
+            Some(format!("{:?}", secret.key.key_type())),
+            Vec::new(),
+        )
+    }));
+
+    let mut parsed: Vec<Option<Certificate>> = parsed.into_iter().map(Some).collect();
+    let certificates: Vec<Certificate> = order.iter().filter_map(|&bag| parsed[bag].take()).collect();
+    keystore::describe(&mut entries, &certificates);
+    Ok((certificates, entries))
+}
@@ -0,0 +1,61 @@
+use crate::cert::Certificate;
+use crate::error::ParseError;
+use x509_parser::asn1_rs::{Any, Class, FromBer, Tag};
+
+const SIGNED_DATA: &str = "1.2.840.113549.1.7.2";
+
+fn error(reason: impl ToString) -> ParseError {
+    ParseError::Pkcs7 {
+        reason: reason.to_string(),
+    }
+}
+
+// each element of a constructed value with its complete encoding; indefinite-length BER, as
+// Windows exports use, ends in two zero bytes that are not an element
+fn elements(mut data: &[u8]) -> Result<Vec<(Any<'_>, &[u8])>, ParseError> {
+    let mut elements = Vec::new();
+    while !data.is_empty() && !data.starts_with(&[0, 0]) {
+        let (rest, any) = Any::from_ber(data).map_err(error)?;
+        elements.push((any, &data[..data.len() - rest.len()]));
+        data = rest;
+    }
+    Ok(elements)
+}
+
+/// The certificates carried by a PKCS#7 (CMS) SignedData, as in `.p7b` and `.p7c` files, in
+/// the order they are stored.
+pub fn certificates(der: &[u8]) -> Result<Vec<Certificate>, ParseError> {
+    // ContentInfo ::= SEQUENCE { contentType OID, content [0] EXPLICIT SignedData }
+    let (_, content_info) = Any::from_ber(der).map_err(error)?;
+    let content_info = elements(content_info.data)?;
+    let content_type = content_info
+        .first()
+        .and_then(|(any, _)| any.as_oid().ok())
+        .map(|oid| oid.to_id_string())
+        .ok_or_else(|| error("missing content type"))?;
+    if content_type != SIGNED_DATA {
+        return Err(error(format!("content type {} is not SignedData", content_type)));
+    }
+    let content = content_info.get(1).ok_or_else(|| error("missing content"))?;
+    let signed_data = elements(content.0.data)?;
+    let signed_data = signed_data.first().ok_or_else(|| error("empty SignedData"))?;
+
+    // SignedData ::= SEQUENCE { version, digestAlgorithms, encapContentInfo,
+    //     certificates [0] IMPLICIT SET OF CertificateChoices OPTIONAL, crls [1] ..., signerInfos }
+    let mut certificates = Vec::new();
+    for (field, _) in elements(signed_data.0.data)? {
+        if field.class() != Class::ContextSpecific || field.tag() != Tag(0) {
+            continue;
+        }
+        // other CertificateChoices, like attribute certificates, are tagged; plain ones are not
+        for (choice, encoded) in elements(field.data)? {
+            if choice.class() == Class::Universal && choice.tag() == Tag::Sequence {
+                certificates.push(Certificate::from_der(encoded)?);
+            }
+        }
+    }
+    if certificates.is_empty() {
+        return Err(ParseError::NoCertificate);
+    }
+    Ok(certificates)
+}
@@ -0,0 +1,70 @@
+use crate::cert::San;
+use crate::error::Result;
+use crate::inspect::{inspect, Options};
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Debug, Serialize)]
+pub struct Sans {
+    pub common_name: Option<String>,
+    #[serde(rename = "sans")]
+    pub entries: Vec<San>,
+}
+
+impl fmt::Display for Sans {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // without a SAN extension, clients that still honour the subject CN fall back to it
+        if self.entries.is_empty() {
+            return match &self.common_name {
+                Some(cn) => write!(f, "CN:{}", cn),
+                None => Ok(()),
+            };
+        }
+        let lines: Vec<String> = self.entries.iter().map(|san| san.to_string()).collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+/// The SANs of each certificate [`Inspection::selected`](crate::Inspection::selected).
+pub fn sans(domain: &str, options: &Options) -> Result<Vec<Sans>> {
+    let inspection = inspect(domain, options)?;
+    Ok(inspection
+        .selected()
+        .iter()
+        .map(|certificate| Sans {
+            common_name: certificate.common_name.clone(),
+            entries: certificate.sans.clone(),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listing(common_name: Option<&str>, entries: Vec<San>) -> Sans {
+        Sans {
+            common_name: common_name.map(str::to_string),
+            entries,
+        }
+    }
+
+    #[test]
+    fn lists_one_san_per_line() {
+        let entries = vec![
+            San::Dns("leaf.test".to_string()),
+            San::Ip("127.0.0.1".to_string()),
+            San::OtherName("1.3.6.1.4.1.311.20.2.3:user@corp.test".to_string()),
+        ];
+        assert_eq!(
+            listing(Some("leaf.test"), entries).to_string(),
+            "DNS:leaf.test\nIP:127.0.0.1\notherName:1.3.6.1.4.1.311.20.2.3:user@corp.test"
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_common_name_without_sans() {
+        assert_eq!(listing(Some("legacy.test"), vec![]).to_string(), "CN:legacy.test");
+        assert_eq!(listing(None, vec![]).to_string(), "");
+    }
+}
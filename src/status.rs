@@ -0,0 +1,104 @@
+use ssl::{Error, Validity};
+use std::process::ExitCode;
+
+/// The process exit status for each outcome, documented in the README.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    Ok = 0,
+    Error = 1,
+    // 2 is what clap exits with on a usage error
+    Connection = 3,
+    Parse = 4,
+    Verification = 5,
+    ExpiringSoon = 6,
+    Expired = 7,
+    Mismatch = 8,
+}
+
+impl Status {
+    /// The worst certificate decides, so an expired intermediate fails the check.
+    pub fn validity(results: &[Validity], warn_days: i64) -> Status {
+        if results.iter().any(|result| result.expired) {
+            Status::Expired
+        } else if results.iter().any(|result| result.expires_within(warn_days)) {
+            Status::ExpiringSoon
+        } else {
+            Status::Ok
+        }
+    }
+}
+
+impl From<Status> for ExitCode {
+    fn from(status: Status) -> ExitCode {
+        ExitCode::from(status as u8)
+    }
+}
+
+impl From<&Error> for Status {
+    fn from(error: &Error) -> Status {
+        match error {
+            Error::Network(_) | Error::TlsHandshake(_) => Status::Connection,
+            Error::Parse(_) => Status::Parse,
+            Error::Input(_) | Error::Verify(_) | Error::Output { .. } => Status::Error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ssl::error::{InputError, NetworkError, ParseError, VerifyError};
+    use std::io;
+    use time::OffsetDateTime;
+
+    fn validity(days_remaining: i64) -> Validity {
+        let now = OffsetDateTime::now_utc();
+        Validity {
+            alias: None,
+            subject: format!("CN={} days", days_remaining),
+            not_before: now,
+            not_after: now + time::Duration::days(days_remaining),
+            days_remaining,
+            expired: days_remaining < 0,
+        }
+    }
+
+    #[test]
+    fn maps_each_error_kind_to_its_status() {
+        let cases = [
+            (Error::from(InputError::NoStdin), Status::Error),
+            (Error::from(VerifyError::NoTrustAnchors), Status::Error),
+            (
+                Error::Output {
+                    format: "JSON",
+                    reason: String::new(),
+                },
+                Status::Error,
+            ),
+            (
+                Error::from(NetworkError::Connect {
+                    address: "leaf.test".to_string(),
+                    port: 443,
+                    source: io::ErrorKind::ConnectionRefused.into(),
+                }),
+                Status::Connection,
+            ),
+            (Error::from(ParseError::NoCertificate), Status::Parse),
+        ];
+        for (error, status) in cases {
+            assert_eq!(Status::from(&error), status, "{}", error);
+        }
+        assert_eq!(ExitCode::from(Status::Mismatch), ExitCode::from(8));
+    }
+
+    #[test]
+    fn validity_exits_with_the_worst_certificate() {
+        assert_eq!(Status::validity(&[validity(90), validity(400)], 30), Status::Ok);
+        assert_eq!(
+            Status::validity(&[validity(90), validity(10)], 30),
+            Status::ExpiringSoon
+        );
+        assert_eq!(Status::validity(&[validity(10), validity(-1)], 30), Status::Expired);
+        assert_eq!(Status::validity(&[validity(10)], 5), Status::Ok);
+    }
+}
@@ -0,0 +1,264 @@
+use crate::error::InputError;
+use log::debug;
+use std::fmt;
+use std::net::{IpAddr, Ipv6Addr};
+use std::str::FromStr;
+
+pub const DEFAULT_PORT: u16 = 443;
+
+/// A remote endpoint to fetch a certificate from: a hostname or IP literal plus a port.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub host: String,
+    pub port: u16,
+}
+
+impl Target {
+    /// Accepts `host`, `host:port`, `[ipv6]`, `[ipv6]:port` and bare IPv4/IPv6 literals.
+    pub fn parse(input: &str) -> Result<Target, InputError> {
+        let invalid = |reason: String| InputError::Target {
+            input: input.to_string(),
+            reason,
+        };
+        let (host, port) = if let Some(rest) = input.strip_prefix('[') {
+            let (host, rest) = rest
+                .split_once(']')
+                .ok_or_else(|| invalid("unterminated '['".to_string()))?;
+            if host.parse::<Ipv6Addr>().is_err() {
+                return Err(invalid(format!("'{}' is not an IPv6 address", host)));
+            }
+            match rest {
+                "" => (host, None),
+                _ => match rest.strip_prefix(':') {
+                    Some(port) => (host, Some(port)),
+                    None => return Err(invalid(format!("unexpected '{}' after ']'", rest))),
+                },
+            }
+        } else if input.parse::<Ipv6Addr>().is_ok() {
+            (input, None)
+        } else {
+            match input.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (input, None),
+            }
+        };
+
+        let port = match port {
+            Some(port) => port
+                .parse::<u16>()
+                .ok()
+                .filter(|port| *port != 0)
+                .ok_or_else(|| invalid(format!("'{}' is not a port between 1 and 65535", port)))?,
+            None => DEFAULT_PORT,
+        };
+
+        if host.parse::<IpAddr>().is_err() && !is_hostname(host) {
+            return Err(invalid(format!("'{}' is not a valid hostname or IP address", host)));
+        }
+
+        Ok(Target {
+            host: host.to_string(),
+            port,
+        })
+    }
+
+    pub fn ip(&self) -> Option<IpAddr> {
+        self.host.parse().ok()
+    }
+
+    /// The `host:port` form suitable for resolving and connecting, with IPv6 hosts bracketed.
+    pub fn address(&self) -> String {
+        match self.ip() {
+            Some(IpAddr::V6(ip)) => format!("[{}]:{}", ip, self.port),
+            _ => format!("{}:{}", self.host, self.port),
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.address())
+    }
+}
+
+/// A curl-style `host:port:addr` override pinning a target to a specific address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolve {
+    pub host: String,
+    pub port: u16,
+    pub addr: IpAddr,
+}
+
+impl FromStr for Resolve {
+    type Err = InputError;
+
+    fn from_str(input: &str) -> Result<Resolve, InputError> {
+        let invalid = |reason: String| InputError::Resolve {
+            input: input.to_string(),
+            reason,
+        };
+        let mut parts = input.splitn(3, ':');
+        let (host, port, addr) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(host), Some(port), Some(addr)) => (host, port, addr),
+            _ => return Err(invalid("missing fields".to_string())),
+        };
+        let port = port
+            .parse::<u16>()
+            .map_err(|_| invalid(format!("'{}' is not a port", port)))?;
+        let addr = addr
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+            .map_err(|_| invalid(format!("'{}' is not an IP address", addr)))?;
+        Ok(Resolve {
+            host: host.to_string(),
+            port,
+            addr,
+        })
+    }
+}
+
+/// How to reach a target: which SNI name to send and which address to actually connect to.
+#[derive(Debug, Clone, Default)]
+pub struct FetchOptions {
+    pub servername: Option<String>,
+    pub connect: Option<String>,
+    pub resolve: Vec<Resolve>,
+}
+
+impl FetchOptions {
+    pub fn server_name(&self, target: &Target) -> String {
+        self.servername.clone().unwrap_or_else(|| target.host.clone())
+    }
+
+    /// `--connect` wins over `--resolve`, which wins over resolving the target itself.
+    pub fn address(&self, target: &Target) -> Result<String, InputError> {
+        if let Some(connect) = &self.connect {
+            let mut endpoint = Target::parse(connect)?;
+            if !has_port(connect) {
+                endpoint.port = target.port;
+            }
+            return Ok(endpoint.address());
+        }
+        let resolved = self
+            .resolve
+            .iter()
+            .find(|resolve| resolve.host.eq_ignore_ascii_case(&target.host) && resolve.port == target.port);
+        match resolved {
+            Some(resolve) => {
+                debug!("--resolve pins {} to {}", target, resolve.addr);
+                Ok(Target {
+                    host: resolve.addr.to_string(),
+                    port: resolve.port,
+                }
+                .address())
+            }
+            None => Ok(target.address()),
+        }
+    }
+}
+
+fn has_port(input: &str) -> bool {
+    match input.strip_prefix('[') {
+        Some(rest) => rest.contains("]:"),
+        None => input.parse::<Ipv6Addr>().is_err() && input.contains(':'),
+    }
+}
+
+// RFC 1123 labels, allowing single-label names like `localhost` and a trailing root dot
+fn is_hostname(host: &str) -> bool {
+    let host = host.strip_suffix('.').unwrap_or(host);
+    !host.is_empty()
+        && host.len() <= 253
+        && host.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(host: &str, port: u16) -> Target {
+        Target {
+            host: host.to_string(),
+            port,
+        }
+    }
+
+    #[test]
+    fn parses_hostnames_with_and_without_port() {
+        assert_eq!(Target::parse("example.com").unwrap(), target("example.com", 443));
+        assert_eq!(Target::parse("example.com:8443").unwrap(), target("example.com", 8443));
+        assert_eq!(Target::parse("localhost").unwrap(), target("localhost", 443));
+        assert_eq!(Target::parse("vault:6443").unwrap(), target("vault", 6443));
+    }
+
+    #[test]
+    fn parses_ip_literals() {
+        assert_eq!(Target::parse("10.0.0.1").unwrap(), target("10.0.0.1", 443));
+        assert_eq!(Target::parse("10.0.0.1:9443").unwrap(), target("10.0.0.1", 9443));
+        assert_eq!(Target::parse("2001:db8::1").unwrap(), target("2001:db8::1", 443));
+        assert_eq!(Target::parse("[2001:db8::1]").unwrap(), target("2001:db8::1", 443));
+        assert_eq!(Target::parse("[::1]:8443").unwrap(), target("::1", 8443));
+        assert_eq!(Target::parse("[::1]:8443").unwrap().address(), "[::1]:8443");
+    }
+
+    #[test]
+    fn rejects_malformed_targets() {
+        assert!(Target::parse("-").is_err());
+        assert!(Target::parse("").is_err());
+        assert!(Target::parse("example.com:0").is_err());
+        assert!(Target::parse("example.com:https").is_err());
+        assert!(Target::parse("[::1").is_err());
+        assert!(Target::parse("[example.com]:443").is_err());
+        assert!(Target::parse("./certs/leaf.pem").is_err());
+    }
+
+    #[test]
+    fn parses_resolve_overrides() {
+        let resolve: Resolve = "example.com:443:10.0.0.5".parse().unwrap();
+        assert_eq!(resolve.host, "example.com");
+        assert_eq!(resolve.port, 443);
+        assert_eq!(resolve.addr, "10.0.0.5".parse::<IpAddr>().unwrap());
+        let resolve: Resolve = "example.com:8443:[2001:db8::5]".parse().unwrap();
+        assert_eq!(resolve.addr, "2001:db8::5".parse::<IpAddr>().unwrap());
+        assert!("example.com:443".parse::<Resolve>().is_err());
+        assert!("example.com:443:not-an-ip".parse::<Resolve>().is_err());
+    }
+
+    #[test]
+    fn separates_server_name_from_connect_address() {
+        let target = target("example.com", 8443);
+        let options = FetchOptions::default();
+        assert_eq!(options.server_name(&target), "example.com");
+        assert_eq!(options.address(&target).unwrap(), "example.com:8443");
+
+        let options = FetchOptions {
+            servername: Some("www.example.com".to_string()),
+            connect: Some("10.0.0.7".to_string()),
+            resolve: vec!["example.com:8443:10.0.0.5".parse().unwrap()],
+        };
+        assert_eq!(options.server_name(&target), "www.example.com");
+        assert_eq!(options.address(&target).unwrap(), "10.0.0.7:8443");
+
+        let options = FetchOptions {
+            connect: Some("[::1]:9443".to_string()),
+            ..FetchOptions::default()
+        };
+        assert_eq!(options.address(&target).unwrap(), "[::1]:9443");
+
+        let options = FetchOptions {
+            resolve: vec![
+                "example.com:443:10.0.0.4".parse().unwrap(),
+                "EXAMPLE.com:8443:10.0.0.5".parse().unwrap(),
+            ],
+            ..FetchOptions::default()
+        };
+        assert_eq!(options.address(&target).unwrap(), "10.0.0.5:8443");
+    }
+}
@@ -0,0 +1,243 @@
+use crate::error::{NetworkError, Result, TlsHandshakeError};
+use log::{debug, info};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{ring, verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, ClientConnection, DigitallySignedStruct, SignatureScheme};
+use serde::Serialize;
+use std::fmt;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub(crate) const TIMEOUT: Duration = Duration::from_secs(10);
+const ALPN_PROTOCOLS: [&[u8]; 2] = [b"h2", b"http/1.1"];
+
+/// Everything the server presented during the handshake, with the chain kept as raw DER.
+#[derive(Debug, Clone, Serialize)]
+pub struct PeerChain {
+    #[serde(skip)]
+    pub certificates: Vec<Vec<u8>>,
+    pub protocol: String,
+    pub cipher_suite: String,
+    pub alpn: Option<String>,
+}
+
+impl fmt::Display for PeerChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Connection:")?;
+        writeln!(f, "    Protocol: {}", self.protocol)?;
+        writeln!(f, "    Cipher Suite: {}", self.cipher_suite)?;
+        write!(f, "    ALPN: {}", self.alpn.as_deref().unwrap_or("none"))
+    }
+}
+
+// the point of the tool is to look at certificates, including broken ones, so the chain is
+// accepted unconditionally; handshake signatures are still checked against the presented key
+#[derive(Debug)]
+struct AcceptAnyCertificate(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for AcceptAnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+fn client_config() -> Result<ClientConfig, TlsHandshakeError> {
+    let provider = Arc::new(ring::default_provider());
+    let mut config = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(AcceptAnyCertificate(provider)))
+        .with_no_client_auth();
+    config.alpn_protocols = ALPN_PROTOCOLS.iter().map(|protocol| protocol.to_vec()).collect();
+    Ok(config)
+}
+
+fn connect(address: &str) -> Result<TcpStream, NetworkError> {
+    let addrs: Vec<SocketAddr> = address
+        .to_socket_addrs()
+        .map_err(|source| NetworkError::Resolve {
+            address: address.to_string(),
+            source,
+        })?
+        .collect();
+    let mut last_error = None;
+    for addr in addrs {
+        debug!("connecting to {}", addr);
+        match TcpStream::connect_timeout(&addr, TIMEOUT) {
+            Ok(stream) => {
+                // only fails for a zero duration
+                let _ = stream.set_read_timeout(Some(TIMEOUT));
+                let _ = stream.set_write_timeout(Some(TIMEOUT));
+                return Ok(stream);
+            }
+            Err(e) => {
+                debug!("connecting to {} failed: {}", addr, e);
+                last_error = Some((addr, e));
+            }
+        }
+    }
+    match last_error {
+        Some((addr, source)) => Err(NetworkError::Connect {
+            address: address.to_string(),
+            port: addr.port(),
+            source,
+        }),
+        None => Err(NetworkError::NoAddresses {
+            address: address.to_string(),
+        }),
+    }
+}
+
+/// Performs a TLS handshake with `address` (a `host:port` pair), sending `server_name` as SNI,
+/// and returns the presented chain along with the negotiated session parameters.
+pub fn fetch_peer_chain(server_name: &str, address: &str) -> Result<PeerChain> {
+    let name = ServerName::try_from(server_name.to_string()).map_err(|e| TlsHandshakeError::ServerName {
+        server_name: server_name.to_string(),
+        reason: e.to_string(),
+    })?;
+    let mut conn = ClientConnection::new(Arc::new(client_config()?), name).map_err(TlsHandshakeError::Config)?;
+    info!("connecting to {} with SNI {}", address, server_name);
+    let mut stream = connect(address)?;
+
+    while conn.is_handshaking() {
+        conn.complete_io(&mut stream)
+            .map_err(|source| TlsHandshakeError::Handshake {
+                address: address.to_string(),
+                server_name: server_name.to_string(),
+                source,
+            })?;
+    }
+
+    let certificates: Vec<Vec<u8>> = conn
+        .peer_certificates()
+        .map(|certs| certs.iter().map(|cert| cert.to_vec()).collect())
+        .unwrap_or_default();
+    if certificates.is_empty() {
+        return Err(TlsHandshakeError::NoCertificate {
+            address: address.to_string(),
+        }
+        .into());
+    }
+
+    let protocol = conn
+        .protocol_version()
+        .map(|version| format!("{:?}", version))
+        .unwrap_or_default();
+    let cipher_suite = conn
+        .negotiated_cipher_suite()
+        .map(|suite| format!("{:?}", suite.suite()))
+        .unwrap_or_default();
+    let alpn = conn
+        .alpn_protocol()
+        .map(|protocol| String::from_utf8_lossy(protocol).to_string());
+
+    info!(
+        "{} presented {} certificate(s) over {} ({})",
+        address,
+        certificates.len(),
+        protocol,
+        cipher_suite
+    );
+
+    conn.send_close_notify();
+    let _ = conn.complete_io(&mut stream);
+
+    Ok(PeerChain {
+        certificates,
+        protocol,
+        cipher_suite,
+        alpn,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustls::pki_types::PrivateKeyDer;
+    use rustls::{ServerConfig, ServerConnection};
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn start_server(alpn: &[&[u8]]) -> (String, Vec<u8>) {
+        let key = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let der = key.cert.der().to_vec();
+        let private_key = PrivateKeyDer::try_from(key.signing_key.serialize_der()).unwrap();
+        let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(vec![key.cert.der().clone()], private_key)
+            .unwrap();
+        config.alpn_protocols = alpn.iter().map(|protocol| protocol.to_vec()).collect();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut conn = ServerConnection::new(Arc::new(config)).unwrap();
+            while conn.is_handshaking() {
+                if conn.complete_io(&mut stream).is_err() {
+                    return;
+                }
+            }
+            let _ = conn.complete_io(&mut stream);
+        });
+        (address, der)
+    }
+
+    #[test]
+    fn fetches_presented_chain_and_session() {
+        let (address, der) = start_server(&[b"h2"]);
+        let chain = fetch_peer_chain("localhost", &address).unwrap();
+        assert_eq!(chain.certificates, vec![der]);
+        assert_eq!(chain.protocol, "TLSv1_3");
+        assert!(chain.cipher_suite.starts_with("TLS13_"));
+        assert_eq!(chain.alpn.as_deref(), Some("h2"));
+    }
+
+    #[test]
+    fn reports_missing_alpn() {
+        let (address, _) = start_server(&[]);
+        let chain = fetch_peer_chain("localhost", &address).unwrap();
+        assert_eq!(chain.alpn, None);
+    }
+
+    #[test]
+    fn fails_cleanly_when_nothing_listens() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        drop(listener);
+        assert!(fetch_peer_chain("localhost", &address).is_err());
+    }
+}
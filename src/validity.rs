@@ -0,0 +1,112 @@
+use crate::cert::format_time;
+use crate::error::Result;
+use crate::format::Format;
+use crate::inspect::{inspect, Options, Selection};
+use serde::Serialize;
+use std::fmt;
+use time::OffsetDateTime;
+
+#[derive(Debug, Serialize)]
+pub struct Validity {
+    /// The keystore entry the certificate belongs to, if any.
+    pub alias: Option<String>,
+    pub subject: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub not_before: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub not_after: OffsetDateTime,
+    pub days_remaining: i64,
+    pub expired: bool,
+}
+
+impl Validity {
+    /// Still valid, but with fewer than `days` days left.
+    pub fn expires_within(&self, days: i64) -> bool {
+        !self.expired && self.days_remaining < days
+    }
+}
+
+impl fmt::Display for Validity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(alias) = &self.alias {
+            writeln!(f, "alias:          {}", alias)?;
+        }
+        writeln!(f, "subject:        {}", self.subject)?;
+        writeln!(f, "issued:         {}", format_time(self.not_before))?;
+        writeln!(f, "expires:        {}", format_time(self.not_after))?;
+        writeln!(f, "days remaining: {}", self.days_remaining)?;
+        write!(f, "expired:        {}", if self.expired { "yes" } else { "no" })
+    }
+}
+
+/// The validity window of each certificate [`Inspection::selected`](crate::Inspection::selected),
+/// except that a Java keystore's entries are unrelated, so without a selection its soonest
+/// expiring certificate is reported instead of the first. PKCS#12 archives report their leaf.
+pub fn validity(domain: &str, options: &Options) -> Result<Vec<Validity>> {
+    let inspection = inspect(domain, options)?;
+    let now = OffsetDateTime::now_utc();
+    let keystore = matches!(inspection.format, Some(Format::Jks | Format::Jceks));
+    let positions: Vec<usize> = if keystore && !options.chain && options.select == Selection::Leaf {
+        (0..inspection.certificates.len())
+            .min_by_key(|&position| inspection.certificates[position].not_after)
+            .into_iter()
+            .collect()
+    } else {
+        (0..inspection.selected().len())
+            .map(|index| inspection.offset() + index)
+            .collect()
+    };
+    Ok(positions
+        .into_iter()
+        .map(|position| {
+            let certificate = &inspection.certificates[position];
+            Validity {
+                alias: inspection.alias(position).map(str::to_string),
+                subject: certificate.subject.clone(),
+                not_before: certificate.not_before,
+                not_after: certificate.not_after,
+                days_remaining: (certificate.not_after - now).whole_days(),
+                expired: now > certificate.not_after,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    fn validity(days_remaining: i64, alias: Option<&str>) -> Validity {
+        Validity {
+            alias: alias.map(str::to_string),
+            subject: "CN=leaf.test".to_string(),
+            not_before: datetime!(2025-01-02 03:04:05 UTC),
+            not_after: datetime!(2026-01-02 03:04:05 UTC),
+            days_remaining,
+            expired: days_remaining < 0,
+        }
+    }
+
+    #[test]
+    fn prints_one_aligned_line_per_field() {
+        assert_eq!(
+            validity(12, None).to_string(),
+            "subject:        CN=leaf.test\n\
+             issued:         2025-01-02 03:04:05 UTC\n\
+             expires:        2026-01-02 03:04:05 UTC\n\
+             days remaining: 12\n\
+             expired:        no"
+        );
+        let keystore = validity(-3, Some("server")).to_string();
+        assert!(keystore.starts_with("alias:          server\nsubject:"));
+        assert!(keystore.ends_with("expired:        yes"));
+    }
+
+    #[test]
+    fn expiring_soon_excludes_expired_certificates() {
+        assert!(validity(10, None).expires_within(30));
+        assert!(!validity(30, None).expires_within(30));
+        assert!(!validity(-1, None).expires_within(30));
+    }
+}
@@ -0,0 +1,654 @@
+use crate::cert::{format_time, Certificate};
+use crate::error::{InputError, Result, VerifyError};
+use crate::format;
+use crate::hostname;
+use crate::inspect::{inspect, Options, Selection};
+use log::{debug, info, warn};
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use time::OffsetDateTime;
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+// guards against issuer loops in hostile or misconfigured bundles
+const MAX_DEPTH: usize = 10;
+
+/// Where a certificate in the verified path came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    Presented,
+    TrustStore,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Presented => write!(f, "presented"),
+            Source::TrustStore => write!(f, "trust store"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PathEntry {
+    pub subject: String,
+    pub source: Source,
+}
+
+/// A single check that failed while validating the path, naming the certificate at fault.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "check", rename_all = "snake_case")]
+pub enum Failure {
+    Expired {
+        depth: usize,
+        subject: String,
+        not_after: String,
+    },
+    NotYetValid {
+        depth: usize,
+        subject: String,
+        not_before: String,
+    },
+    NameMismatch {
+        name: String,
+    },
+    UnknownIssuer {
+        depth: usize,
+        issuer: String,
+    },
+    UntrustedRoot {
+        depth: usize,
+        subject: String,
+    },
+    BadSignature {
+        depth: usize,
+        subject: String,
+    },
+    NotCa {
+        depth: usize,
+        subject: String,
+    },
+    PathLenExceeded {
+        depth: usize,
+        subject: String,
+        path_len: u32,
+        intermediates: usize,
+    },
+    PathTooLong {
+        depth: usize,
+        max_depth: usize,
+    },
+    KeyUsage {
+        depth: usize,
+        subject: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::Expired {
+                depth,
+                subject,
+                not_after,
+            } => {
+                write!(f, "depth {} ({}): expired at {}", depth, subject, not_after)
+            }
+            Failure::NotYetValid {
+                depth,
+                subject,
+                not_before,
+            } => {
+                write!(f, "depth {} ({}): not valid until {}", depth, subject, not_before)
+            }
+            Failure::NameMismatch { name } => write!(f, "depth 0: certificate is not valid for '{}'", name),
+            Failure::UnknownIssuer { depth, issuer } => {
+                write!(
+                    f,
+                    "depth {}: issuer {} not found in presented chain or trust store",
+                    depth, issuer
+                )
+            }
+            Failure::UntrustedRoot { depth, subject } => {
+                write!(
+                    f,
+                    "depth {} ({}): self-signed root is not in the trust store",
+                    depth, subject
+                )
+            }
+            Failure::BadSignature { depth, subject } => {
+                write!(
+                    f,
+                    "depth {} ({}): signature does not verify against its issuer",
+                    depth, subject
+                )
+            }
+            Failure::NotCa { depth, subject } => {
+                write!(
+                    f,
+                    "depth {} ({}): issues certificates but is not a CA (basicConstraints)",
+                    depth, subject
+                )
+            }
+            Failure::PathLenExceeded {
+                depth,
+                subject,
+                path_len,
+                intermediates,
+            } => write!(
+                f,
+                "depth {} ({}): pathLen {} exceeded by {} intermediate(s) below it",
+                depth, subject, path_len, intermediates
+            ),
+            Failure::PathTooLong { depth, max_depth } => write!(
+                f,
+                "depth {}: no trust anchor within {} issuers; giving up",
+                depth, max_depth
+            ),
+            Failure::KeyUsage { depth, subject, reason } => write!(f, "depth {} ({}): {}", depth, subject, reason),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Verification {
+    pub path: Vec<PathEntry>,
+    pub failures: Vec<Failure>,
+}
+
+impl Verification {
+    pub fn is_valid(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl Serialize for Verification {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Verification", 3)?;
+        state.serialize_field("verified", &self.is_valid())?;
+        state.serialize_field("path", &self.path)?;
+        state.serialize_field("failures", &self.failures)?;
+        state.end()
+    }
+}
+
+impl fmt::Display for Verification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "path:")?;
+        for (depth, entry) in self.path.iter().enumerate() {
+            writeln!(f, "    {}: {} ({})", depth, entry.subject, entry.source)?;
+        }
+        if self.is_valid() {
+            return write!(f, "verified: yes");
+        }
+        write!(f, "verified: no")?;
+        for failure in &self.failures {
+            write!(f, "\n    {}", failure)?;
+        }
+        Ok(())
+    }
+}
+
+/// The set of trust anchors a path must terminate in.
+#[derive(Debug, Clone, Default)]
+pub struct TrustStore {
+    pub anchors: Vec<Certificate>,
+}
+
+impl TrustStore {
+    /// Uses `--ca-file`/`--ca-dir` when given, otherwise the platform's trust store.
+    pub fn load(ca_file: Option<&Path>, ca_dir: Option<&Path>) -> Result<TrustStore> {
+        if ca_file.is_none() && ca_dir.is_none() {
+            return Ok(TrustStore::system()?);
+        }
+        let mut anchors = Vec::new();
+        if let Some(ca_file) = ca_file {
+            anchors.extend(format::certificates(&fs::read(ca_file).map_err(InputError::read(ca_file))?)?.1);
+        }
+        if let Some(ca_dir) = ca_dir {
+            for entry in fs::read_dir(ca_dir).map_err(InputError::read(ca_dir))? {
+                let path = entry.map_err(InputError::read(ca_dir))?.path();
+                // hashed symlink directories also contain CRLs and other files; skip what doesn't parse
+                if let Some((_, certificates)) = fs::read(&path).ok().and_then(|data| format::certificates(&data).ok())
+                {
+                    anchors.extend(certificates);
+                } else {
+                    debug!("skipping {}, no certificates", path.display());
+                }
+            }
+        }
+        if anchors.is_empty() {
+            return Err(VerifyError::NoTrustAnchors.into());
+        }
+        info!("loaded {} trust anchor(s) from --ca-file/--ca-dir", anchors.len());
+        Ok(TrustStore { anchors })
+    }
+
+    pub fn system() -> Result<TrustStore, VerifyError> {
+        let result = rustls_native_certs::load_native_certs();
+        let anchors: Vec<Certificate> = result
+            .certs
+            .iter()
+            .filter_map(|der| Certificate::from_der(der).ok())
+            .collect();
+        if anchors.is_empty() {
+            let reason = match result.errors.is_empty() {
+                true => "no certificates found".to_string(),
+                false => result
+                    .errors
+                    .iter()
+                    .map(|error| error.to_string())
+                    .collect::<Vec<_>>()
+                    .join("; "),
+            };
+            return Err(VerifyError::SystemStore { reason });
+        }
+        for error in &result.errors {
+            warn!("system trust store: {}", error);
+        }
+        info!("loaded {} trust anchor(s) from the system store", anchors.len());
+        Ok(TrustStore { anchors })
+    }
+
+    fn contains(&self, certificate: &Certificate) -> bool {
+        self.anchors
+            .iter()
+            .any(|anchor| anchor.fingerprint == certificate.fingerprint)
+    }
+}
+
+fn signs(issuer: &Certificate, child: &Certificate) -> bool {
+    match (
+        X509Certificate::from_der(&issuer.der),
+        X509Certificate::from_der(&child.der),
+    ) {
+        (Ok((_, issuer)), Ok((_, child))) => child.verify_signature(Some(issuer.public_key())).is_ok(),
+        _ => false,
+    }
+}
+
+/// Builds a path from `chain[0]` through the presented certificates to an anchor in `store`,
+/// then checks validity periods, signatures, basicConstraints, pathLen, keyUsage and `name`
+/// (using the RFC 6125 rules in [`hostname::check`]).
+pub fn verify_chain(chain: &[Certificate], store: &TrustStore, name: Option<&str>, legacy_cn: bool) -> Verification {
+    let mut path: Vec<(&Certificate, Source)> = vec![(&chain[0], Source::Presented)];
+    let mut used = vec![0];
+    let mut failures = Vec::new();
+
+    loop {
+        let (current, source) = path[path.len() - 1];
+        if source == Source::TrustStore || store.contains(current) {
+            break;
+        }
+        let depth = path.len() - 1;
+        if depth >= MAX_DEPTH {
+            failures.push(Failure::PathTooLong {
+                depth,
+                max_depth: MAX_DEPTH,
+            });
+            break;
+        }
+        let anchor = store
+            .anchors
+            .iter()
+            .find(|anchor| anchor.subject == current.issuer && signs(anchor, current));
+        if let Some(anchor) = anchor {
+            path.push((anchor, Source::TrustStore));
+            continue;
+        }
+        let candidates: Vec<usize> = (0..chain.len())
+            .filter(|index| !used.contains(index) && chain[*index].subject == current.issuer)
+            .collect();
+        match candidates.iter().find(|index| signs(&chain[**index], current)) {
+            Some(index) => {
+                used.push(*index);
+                path.push((&chain[*index], Source::Presented));
+            }
+            None if !candidates.is_empty() => {
+                failures.push(Failure::BadSignature {
+                    depth,
+                    subject: current.subject.clone(),
+                });
+                used.push(candidates[0]);
+                path.push((&chain[candidates[0]], Source::Presented));
+            }
+            None if current.issuer == current.subject => {
+                failures.push(Failure::UntrustedRoot {
+                    depth,
+                    subject: current.subject.clone(),
+                });
+                break;
+            }
+            None => {
+                failures.push(Failure::UnknownIssuer {
+                    depth,
+                    issuer: current.issuer.clone(),
+                });
+                break;
+            }
+        }
+    }
+
+    let now = OffsetDateTime::now_utc();
+    for (depth, (certificate, _)) in path.iter().enumerate() {
+        let subject = certificate.subject.clone();
+        if now < certificate.not_before {
+            failures.push(Failure::NotYetValid {
+                depth,
+                subject: subject.clone(),
+                not_before: format_time(certificate.not_before),
+            });
+        }
+        if now > certificate.not_after {
+            failures.push(Failure::Expired {
+                depth,
+                subject: subject.clone(),
+                not_after: format_time(certificate.not_after),
+            });
+        }
+        let Ok((_, x509)) = X509Certificate::from_der(&certificate.der) else {
+            continue;
+        };
+        let key_usage = x509.key_usage().ok().flatten().map(|ext| ext.value);
+        if depth == 0 {
+            if key_usage.is_some_and(|ku| !(ku.digital_signature() || ku.key_encipherment() || ku.key_agreement())) {
+                failures.push(Failure::KeyUsage {
+                    depth,
+                    subject: subject.clone(),
+                    reason: "keyUsage does not permit TLS server authentication".to_string(),
+                });
+            }
+            let eku = x509.extended_key_usage().ok().flatten().map(|ext| ext.value);
+            if eku.is_some_and(|eku| !(eku.server_auth || eku.any)) {
+                failures.push(Failure::KeyUsage {
+                    depth,
+                    subject,
+                    reason: "extendedKeyUsage does not include serverAuth".to_string(),
+                });
+            }
+            continue;
+        }
+        let constraints = x509.basic_constraints().ok().flatten().map(|ext| ext.value);
+        match constraints {
+            Some(bc) if bc.ca => {
+                let intermediates = depth - 1;
+                if let Some(path_len) = bc.path_len_constraint.filter(|len| (*len as usize) < intermediates) {
+                    failures.push(Failure::PathLenExceeded {
+                        depth,
+                        subject: subject.clone(),
+                        path_len,
+                        intermediates,
+                    });
+                }
+            }
+            _ => failures.push(Failure::NotCa {
+                depth,
+                subject: subject.clone(),
+            }),
+        }
+        if key_usage.is_some_and(|ku| !ku.key_cert_sign()) {
+            failures.push(Failure::KeyUsage {
+                depth,
+                subject,
+                reason: "keyUsage does not include keyCertSign".to_string(),
+            });
+        }
+    }
+
+    if let Some(name) = name {
+        if !hostname::check(&chain[0], name, legacy_cn).is_match() {
+            failures.push(Failure::NameMismatch { name: name.to_string() });
+        }
+    }
+
+    Verification {
+        path: path
+            .iter()
+            .map(|(certificate, source)| PathEntry {
+                subject: certificate.subject.clone(),
+                source: *source,
+            })
+            .collect(),
+        failures,
+    }
+}
+
+/// Verifies the certificate [`Inspection::selected`](crate::Inspection::selected) in `domain`
+/// against `store`, with the rest of the input as candidate issuers. `hostname` defaults to the
+/// SNI name for hosts, which only applies when the leaf is selected; `--all` is rejected.
+pub fn verify(domain: &str, store: &TrustStore, hostname: Option<&str>, options: &Options) -> Result<Verification> {
+    if options.select == Selection::All && !options.chain {
+        return Err(InputError::SelectAll { command: "verify" }.into());
+    }
+    let inspection = inspect(domain, options)?;
+    let mut chain = inspection.certificates.clone();
+    let start = chain.remove(inspection.offset());
+    chain.insert(0, start);
+    let name = hostname.or(inspection.server_name.as_deref().filter(|_| inspection.offset() == 0));
+    Ok(verify_chain(&chain, store, name, options.legacy_cn))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::{
+        BasicConstraints, CertificateParams, CertifiedIssuer, DnType, ExtendedKeyUsagePurpose, IsCa, KeyPair,
+        KeyUsagePurpose,
+    };
+    use time::Duration;
+
+    fn ca(
+        name: &str,
+        is_ca: IsCa,
+        issuer: Option<&CertifiedIssuer<'static, KeyPair>>,
+    ) -> CertifiedIssuer<'static, KeyPair> {
+        let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        params.distinguished_name.push(DnType::CommonName, name);
+        params.is_ca = is_ca;
+        let key = KeyPair::generate().unwrap();
+        match issuer {
+            Some(issuer) => CertifiedIssuer::signed_by(params, key, issuer).unwrap(),
+            None => CertifiedIssuer::self_signed(params, key).unwrap(),
+        }
+    }
+
+    fn leaf(issuer: &CertifiedIssuer<'static, KeyPair>, expired: bool) -> Certificate {
+        let mut params = CertificateParams::new(vec!["*.leaf.test".to_string(), "127.0.0.1".to_string()]).unwrap();
+        params.distinguished_name.push(DnType::CommonName, "leaf.test");
+        if expired {
+            params.not_before = OffsetDateTime::now_utc() - Duration::days(30);
+            params.not_after = OffsetDateTime::now_utc() - Duration::days(1);
+        }
+        let cert = params.signed_by(&KeyPair::generate().unwrap(), issuer).unwrap();
+        Certificate::from_der(cert.der()).unwrap()
+    }
+
+    fn certificate(issuer: &CertifiedIssuer<'static, KeyPair>) -> Certificate {
+        Certificate::from_der(issuer.der()).unwrap()
+    }
+
+    #[test]
+    fn verifies_path_through_presented_intermediate() {
+        let root = ca("Root", IsCa::Ca(BasicConstraints::Unconstrained), None);
+        let intermediate = ca("Intermediate", IsCa::Ca(BasicConstraints::Constrained(0)), Some(&root));
+        let store = TrustStore {
+            anchors: vec![certificate(&root)],
+        };
+        let chain = [leaf(&intermediate, false), certificate(&intermediate)];
+        let verification = verify_chain(&chain, &store, Some("www.leaf.test"), false);
+        assert!(verification.is_valid(), "{}", verification);
+        assert_eq!(verification.path.len(), 3);
+        assert_eq!(verification.path[2].source, Source::TrustStore);
+    }
+
+    #[test]
+    fn reports_unknown_issuer_and_name_mismatch() {
+        let root = ca("Root", IsCa::Ca(BasicConstraints::Unconstrained), None);
+        let intermediate = ca("Intermediate", IsCa::Ca(BasicConstraints::Unconstrained), Some(&root));
+        let store = TrustStore {
+            anchors: vec![certificate(&root)],
+        };
+        let verification = verify_chain(&[leaf(&intermediate, false)], &store, Some("leaf.test"), false);
+        assert_eq!(
+            verification.failures,
+            vec![
+                Failure::UnknownIssuer {
+                    depth: 0,
+                    issuer: "CN=Intermediate".to_string()
+                },
+                Failure::NameMismatch {
+                    name: "leaf.test".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_presented_roots_missing_from_the_store() {
+        let root = ca("Root", IsCa::Ca(BasicConstraints::Unconstrained), None);
+        let store = TrustStore {
+            anchors: vec![certificate(&ca(
+                "Other Root",
+                IsCa::Ca(BasicConstraints::Unconstrained),
+                None,
+            ))],
+        };
+        let verification = verify_chain(&[leaf(&root, false), certificate(&root)], &store, None, false);
+        assert_eq!(verification.path.len(), 2);
+        assert_eq!(
+            verification.failures,
+            vec![Failure::UntrustedRoot {
+                depth: 1,
+                subject: "CN=Root".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn reports_expired_leaf_and_non_ca_issuer() {
+        let root = ca("Root", IsCa::Ca(BasicConstraints::Unconstrained), None);
+        let not_ca = ca("Not A CA", IsCa::ExplicitNoCa, Some(&root));
+        let store = TrustStore {
+            anchors: vec![certificate(&root)],
+        };
+        let chain = [leaf(&not_ca, true), certificate(&not_ca)];
+        let verification = verify_chain(&chain, &store, Some("127.0.0.1"), false);
+        assert!(matches!(verification.failures[0], Failure::Expired { depth: 0, .. }));
+        assert_eq!(
+            verification.failures[1],
+            Failure::NotCa {
+                depth: 1,
+                subject: "CN=Not A CA".to_string()
+            }
+        );
+        assert_eq!(verification.failures.len(), 2);
+    }
+
+    #[test]
+    fn reports_bad_signature() {
+        let root = ca("Root", IsCa::Ca(BasicConstraints::Unconstrained), None);
+        let rekeyed = ca("Root", IsCa::Ca(BasicConstraints::Unconstrained), None);
+        let store = TrustStore {
+            anchors: vec![certificate(&rekeyed)],
+        };
+        let verification = verify_chain(&[leaf(&root, false), certificate(&rekeyed)], &store, None, false);
+        assert_eq!(
+            verification.failures,
+            vec![Failure::BadSignature {
+                depth: 0,
+                subject: "CN=leaf.test".to_string()
+            }]
+        );
+        assert_eq!(verification.path.len(), 2);
+    }
+
+    #[test]
+    fn reports_key_usage_violations() {
+        let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        params.distinguished_name.push(DnType::CommonName, "Signing Only");
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+        let root = CertifiedIssuer::self_signed(params, KeyPair::generate().unwrap()).unwrap();
+
+        let mut params = CertificateParams::new(vec!["client.test".to_string()]).unwrap();
+        params.distinguished_name.push(DnType::CommonName, "client.test");
+        params.key_usages = vec![KeyUsagePurpose::ContentCommitment];
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+        let client = params.signed_by(&KeyPair::generate().unwrap(), &root).unwrap();
+
+        let store = TrustStore {
+            anchors: vec![certificate(&root)],
+        };
+        let chain = [Certificate::from_der(client.der()).unwrap()];
+        let verification = verify_chain(&chain, &store, None, false);
+        let reasons: Vec<(usize, &str)> = verification
+            .failures
+            .iter()
+            .map(|failure| match failure {
+                Failure::KeyUsage { depth, reason, .. } => (*depth, reason.as_str()),
+                other => panic!("unexpected failure: {}", other),
+            })
+            .collect();
+        assert_eq!(
+            reasons,
+            [
+                (0, "keyUsage does not permit TLS server authentication"),
+                (0, "extendedKeyUsage does not include serverAuth"),
+                (1, "keyUsage does not include keyCertSign"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_path_len_violation() {
+        let root = ca("Root", IsCa::Ca(BasicConstraints::Constrained(0)), None);
+        let intermediate = ca("Intermediate", IsCa::Ca(BasicConstraints::Unconstrained), Some(&root));
+        let store = TrustStore {
+            anchors: vec![certificate(&root)],
+        };
+        let chain = [leaf(&intermediate, false), certificate(&intermediate)];
+        let verification = verify_chain(&chain, &store, None, false);
+        assert_eq!(
+            verification.failures,
+            vec![Failure::PathLenExceeded {
+                depth: 2,
+                subject: "CN=Root".to_string(),
+                path_len: 0,
+                intermediates: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn gives_up_on_paths_longer_than_the_depth_limit() {
+        let mut issuers = vec![ca("CA 0", IsCa::Ca(BasicConstraints::Unconstrained), None)];
+        for index in 1..=MAX_DEPTH {
+            let issuer = ca(
+                &format!("CA {}", index),
+                IsCa::Ca(BasicConstraints::Unconstrained),
+                issuers.last(),
+            );
+            issuers.push(issuer);
+        }
+        let mut chain = vec![leaf(issuers.last().unwrap(), false)];
+        chain.extend(issuers.iter().rev().map(certificate));
+        let store = TrustStore {
+            anchors: vec![certificate(&ca(
+                "Unrelated",
+                IsCa::Ca(BasicConstraints::Unconstrained),
+                None,
+            ))],
+        };
+        let verification = verify_chain(&chain, &store, None, false);
+        assert!(!verification.is_valid());
+        assert_eq!(verification.path.len(), MAX_DEPTH + 1);
+        assert!(verification.failures.contains(&Failure::PathTooLong {
+            depth: MAX_DEPTH,
+            max_depth: MAX_DEPTH
+        }));
+    }
+}
@@ -0,0 +1,489 @@
+use ssl::backend::{Fake, Fixtures, Recorder};
+use ssl::error::{InputError, NetworkError, ParseError};
+use ssl::format::Format;
+use ssl::keystore::{EntryKind, Password};
+use ssl::target::{FetchOptions, Resolve};
+use ssl::verify::{Failure, TrustStore};
+use ssl::{compare, inspect, matches, sans, validity, verify, Certificate, Error, InputKind, Options, Selection};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+fn fixture(name: &str) -> String {
+    format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+fn pem(name: &str) -> Vec<u8> {
+    fs::read(fixture(name)).unwrap()
+}
+
+fn options(backend: Fake) -> Options {
+    Options {
+        backend: Arc::new(backend),
+        ..Options::default()
+    }
+}
+
+fn options_with_stdin(options: &Options, stdin: Vec<u8>) -> Options {
+    Options {
+        backend: Arc::new(Fake::new().with_stdin(stdin)),
+        ..options.clone()
+    }
+}
+
+#[test]
+fn reads_a_file_without_touching_the_backend() {
+    let inspection = inspect(&fixture("chain.pem"), &options(Fake::new())).unwrap();
+    assert_eq!(inspection.certificates.len(), 3);
+    assert_eq!(inspection.certificates[0].subject, "CN=leaf.test");
+    assert!(inspection.session.is_none());
+    assert!(inspection.name_match.is_none());
+}
+
+#[test]
+fn fetches_a_host_through_the_backend() {
+    let fake = Fake::new().with_pem("leaf.test", &pem("chain.pem")).unwrap();
+    let inspection = inspect("leaf.test:8443", &options(fake)).unwrap();
+    assert_eq!(inspection.server_name.as_deref(), Some("leaf.test"));
+    assert_eq!(inspection.session.unwrap().protocol, "TLSv1_3");
+    assert!(inspection.name_match.unwrap().is_match());
+    assert_eq!(inspection.certificates.len(), 3);
+}
+
+#[test]
+fn routes_fetches_through_servername_connect_and_resolve() {
+    let fake = Arc::new(Fake::new().with_pem("leaf.test", &pem("leaf.pem")).unwrap());
+    let mut options = Options {
+        backend: fake.clone(),
+        fetch: FetchOptions {
+            servername: Some("leaf.test".to_string()),
+            ..FetchOptions::default()
+        },
+        ..Options::default()
+    };
+    inspect("10.0.0.1", &options).unwrap();
+    options.fetch.servername = None;
+    options.fetch.resolve = vec!["leaf.test:443:10.0.0.2".parse::<Resolve>().unwrap()];
+    inspect("leaf.test", &options).unwrap();
+    options.fetch.connect = Some("[::1]:9443".to_string());
+    inspect("leaf.test", &options).unwrap();
+    assert_eq!(
+        fake.fetched(),
+        vec![
+            ("leaf.test".to_string(), "10.0.0.1:443".to_string()),
+            ("leaf.test".to_string(), "10.0.0.2:443".to_string()),
+            ("leaf.test".to_string(), "[::1]:9443".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn reports_unreachable_hosts_as_network_errors() {
+    let result = inspect("nowhere.test", &options(Fake::new()));
+    assert!(matches!(
+        result,
+        Err(Error::Network(NetworkError::Connect { port: 443, .. }))
+    ));
+}
+
+#[test]
+fn reads_stdin_when_the_input_is_neither_file_nor_target() {
+    let inspection = inspect("-", &options(Fake::new().with_stdin(pem("leaf.pem")))).unwrap();
+    assert_eq!(inspection.certificates[0].subject, "CN=leaf.test");
+    assert!(inspection.session.is_none());
+}
+
+#[test]
+fn rejects_input_that_matches_nothing() {
+    let result = inspect("not a target", &options(Fake::new().with_stdin("  \n")));
+    assert!(matches!(result, Err(Error::Input(InputError::Unrecognized { .. }))));
+}
+
+#[test]
+fn dash_and_forced_stdin_never_fall_back_to_files_or_hosts() {
+    let fake = Fake::new().with_pem("leaf.test", &pem("leaf.pem")).unwrap();
+    let result = inspect("-", &options(fake));
+    assert!(matches!(result, Err(Error::Input(InputError::NoStdin))));
+
+    let options = Options {
+        input: InputKind::Stdin,
+        ..options(Fake::new().with_stdin(pem("nosan.pem")))
+    };
+    let inspection = inspect(&fixture("leaf.pem"), &options).unwrap();
+    assert_eq!(inspection.certificates[0].subject, "O=Fixture, CN=nosan.test");
+}
+
+#[test]
+fn forced_kinds_override_the_guess() {
+    let fake = Fake::new().with_pem("leaf.test", &pem("leaf.pem")).unwrap();
+    let options = Options {
+        input: InputKind::File,
+        ..options(fake)
+    };
+    let result = inspect("leaf.test", &options);
+    assert!(matches!(result, Err(Error::Input(InputError::Read { .. }))));
+
+    let options = Options {
+        input: InputKind::Host,
+        ..options
+    };
+    assert!(inspect("leaf.test", &options).unwrap().session.is_some());
+    let result = inspect(&fixture("leaf.pem"), &options);
+    assert!(matches!(result, Err(Error::Input(InputError::Target { .. }))));
+}
+
+#[test]
+fn reports_files_without_certificates_as_parse_errors() {
+    let result = inspect(&fixture("not-a-cert.txt"), &options(Fake::new()));
+    assert!(matches!(result, Err(Error::Parse(ParseError::NoCertificate))));
+}
+
+#[test]
+fn sans_validity_and_compare_share_the_backend() {
+    let fake = Fake::new()
+        .with_pem("leaf.test", &pem("leaf.pem"))
+        .unwrap()
+        .with_pem("nosan.test", &pem("nosan.pem"))
+        .unwrap();
+    let options = options(fake);
+
+    let entries: Vec<String> = sans("leaf.test", &options).unwrap()[0]
+        .entries
+        .iter()
+        .map(|san| san.to_string())
+        .collect();
+    assert_eq!(entries, ["DNS:leaf.test", "DNS:*.leaf.test", "IP:127.0.0.1"]);
+
+    assert!(!validity("leaf.test", &options).unwrap()[0].expired);
+    assert!(validity(&fixture("expired.pem"), &options).unwrap()[0].expired);
+
+    assert!(compare("leaf.test", &fixture("leaf.pem"), &options).unwrap().matches());
+    let comparison = compare("leaf.test", "nosan.test", &options).unwrap();
+    assert!(comparison
+        .differences
+        .iter()
+        .any(|difference| difference.field == "subject"));
+}
+
+#[test]
+fn replays_recorded_fixtures() {
+    let options = Options {
+        backend: Arc::new(Fixtures::new(fixture("hosts"))),
+        chain: true,
+        ..Options::default()
+    };
+    let inspection = inspect("leaf.test", &options).unwrap();
+    let session = inspection.session.unwrap();
+    assert_eq!(session.cipher_suite, "TLS13_AES_256_GCM_SHA384");
+    assert_eq!(session.alpn.as_deref(), Some("h2"));
+    assert_eq!(inspection.certificates.len(), 2);
+}
+
+#[test]
+fn records_fixtures_that_replay_identically() {
+    let dir: PathBuf = std::env::temp_dir().join(format!("ssl-fixtures-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let fake = Fake::new().with_pem("leaf.test", &pem("chain.pem")).unwrap();
+    let recorded = inspect(
+        "leaf.test",
+        &Options {
+            backend: Arc::new(Recorder::new(fake, &dir)),
+            ..Options::default()
+        },
+    )
+    .unwrap();
+    let replayed = inspect(
+        "leaf.test",
+        &Options {
+            backend: Arc::new(Fixtures::new(&dir)),
+            ..Options::default()
+        },
+    )
+    .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(
+        recorded.session.as_ref().unwrap().protocol,
+        replayed.session.as_ref().unwrap().protocol
+    );
+    let fingerprints = |inspection: &ssl::Inspection| {
+        inspection
+            .certificates
+            .iter()
+            .map(|certificate| certificate.fingerprint.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(fingerprints(&recorded), fingerprints(&replayed));
+}
+
+#[test]
+fn sniffs_der_and_bare_base64_in_files_and_stdin() {
+    for name in ["leaf.der", "leaf.b64"] {
+        let inspection = inspect(&fixture(name), &options(Fake::new())).unwrap();
+        assert_eq!(inspection.certificates[0].subject, "CN=leaf.test");
+    }
+    let from_der = inspect("-", &options(Fake::new().with_stdin(pem("leaf.der")))).unwrap();
+    let from_pem = inspect(&fixture("leaf.pem"), &options(Fake::new())).unwrap();
+    assert_eq!(
+        from_der.certificates[0].fingerprint,
+        from_pem.certificates[0].fingerprint
+    );
+}
+
+#[test]
+fn selects_certificates_from_a_bundle_by_index_or_all() {
+    let subjects = |select: Selection| {
+        let options = Options {
+            select,
+            ..options(Fake::new())
+        };
+        validity(&fixture("chain.pem"), &options)
+            .map(|results| results.into_iter().map(|result| result.subject).collect::<Vec<_>>())
+    };
+    assert_eq!(subjects(Selection::Leaf).unwrap(), ["CN=leaf.test"]);
+    assert_eq!(
+        subjects(Selection::Index(2)).unwrap(),
+        ["O=Fixture, CN=Fixture Root CA"]
+    );
+    assert_eq!(subjects(Selection::All).unwrap().len(), 3);
+    assert!(matches!(
+        subjects(Selection::Index(3)),
+        Err(Error::Input(InputError::Index { index: 3, count: 3 }))
+    ));
+}
+
+#[test]
+fn reads_pkcs12_archives_with_and_without_a_password() {
+    let inspection = inspect(&fixture("nopass.p12"), &options(Fake::new())).unwrap();
+    assert_eq!(inspection.format, Some(Format::Pkcs12));
+    assert_eq!(inspection.certificates[0].subject, "CN=p12.test");
+
+    let protected = options(Fake::new().with_password("changeit"));
+    let inspection = inspect(&fixture("bundle.p12"), &protected).unwrap();
+    let entry = &inspection.entries[0];
+    assert_eq!(entry.alias.as_deref(), Some("server"));
+    assert_eq!(entry.kind, EntryKind::PrivateKey);
+    assert_eq!(entry.key_algorithm.as_deref(), Some("id-ecPublicKey"));
+    assert_eq!(entry.certificates, [0, 1]);
+    assert_eq!(inspection.entries[1].alias.as_deref(), Some("fixture ca"));
+    assert_eq!(inspection.certificates[1].subject, "O=Fixture, CN=Fixture PKCS12 CA");
+    let json = serde_json::to_string(&inspection).unwrap();
+    assert!(!json.contains("PRIVATE KEY"));
+}
+
+#[test]
+fn reports_missing_and_wrong_pkcs12_passwords() {
+    let result = inspect(&fixture("bundle.p12"), &options(Fake::new()));
+    assert!(matches!(result, Err(Error::Input(InputError::Password { .. }))));
+
+    let result = inspect(&fixture("bundle.p12"), &options(Fake::new().with_password("wrong")));
+    assert!(matches!(result, Err(Error::Input(InputError::WrongPassword))));
+
+    let dir = std::env::temp_dir().join(format!("ssl-password-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("password"), "changeit\n").unwrap();
+    let from_file = Options {
+        password: Password::File(dir.join("password")),
+        ..options(Fake::new())
+    };
+    let result = inspect(&fixture("bundle.p12"), &from_file);
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(result.unwrap().certificates.len(), 2);
+}
+
+#[test]
+fn reads_pkcs7_bundles_in_der_pem_and_indefinite_length_ber() {
+    let options = Options {
+        select: Selection::All,
+        ..options(Fake::new())
+    };
+    for name in ["chain.p7b", "chain-pem.p7b"] {
+        let inspection = inspect(&fixture(name), &options).unwrap();
+        assert_eq!(inspection.format, Some(Format::Pkcs7));
+        let subjects: Vec<&str> = inspection
+            .selected()
+            .iter()
+            .map(|certificate| certificate.subject.as_str())
+            .collect();
+        assert_eq!(
+            subjects,
+            [
+                "CN=leaf.test",
+                "O=Fixture, CN=Fixture Intermediate CA",
+                "O=Fixture, CN=Fixture Root CA"
+            ]
+        );
+    }
+    let mixed = [pem("nosan.pem"), pem("chain-pem.p7b")].concat();
+    let inspection = inspect("-", &options_with_stdin(&options, mixed)).unwrap();
+    assert_eq!(inspection.certificates.len(), 4);
+    assert_eq!(inspection.certificates[0].subject, "O=Fixture, CN=nosan.test");
+
+    let noted = [b"note: -----BEGIN PKCS7----- follows\n".to_vec(), pem("leaf.pem")].concat();
+    let inspection = inspect("-", &options_with_stdin(&options, noted)).unwrap();
+    assert_eq!(inspection.certificates[0].subject, "CN=leaf.test");
+    let empty = b"note: -----BEGIN PKCS7----- and nothing else\n".to_vec();
+    let result = inspect("-", &options_with_stdin(&options, empty));
+    assert!(matches!(result, Err(Error::Parse(ParseError::NoCertificate))));
+
+    let signed = inspect(&fixture("signed.p7m"), &options).unwrap();
+    assert_eq!(signed.certificates[0].subject, "CN=p12.test");
+    assert_eq!(signed.certificates.len(), 2);
+}
+
+#[test]
+fn lists_java_keystore_entries_and_checks_their_integrity() {
+    let protected = options(Fake::new().with_password("changeit"));
+    let inspection = inspect(&fixture("keystore.jceks"), &protected).unwrap();
+    assert_eq!(inspection.format, Some(Format::Jceks));
+    let entries: Vec<(Option<&str>, EntryKind)> = inspection
+        .entries
+        .iter()
+        .map(|entry| (entry.alias.as_deref(), entry.kind))
+        .collect();
+    assert_eq!(
+        entries,
+        [
+            (Some("secret"), EntryKind::SecretKey),
+            (Some("fixture root"), EntryKind::Certificate),
+            (Some("server"), EntryKind::PrivateKey),
+        ]
+    );
+    let server = &inspection.entries[2];
+    assert_eq!(server.key_algorithm.as_deref(), Some("id-ecPublicKey"));
+    assert_eq!(server.subject.as_deref(), Some("CN=p12.test"));
+    assert_eq!(server.certificates, [1, 2]);
+
+    let result = inspect(&fixture("truststore.jks"), &options(Fake::new().with_password("wrong")));
+    assert!(matches!(result, Err(Error::Input(InputError::WrongPassword))));
+    let unverified = inspect(&fixture("truststore.jks"), &options(Fake::new())).unwrap();
+    assert_eq!(unverified.format, Some(Format::Jks));
+    assert_eq!(unverified.entries.len(), 2);
+    let skipped = inspect(&fixture("truststore.jks"), &options(Fake::new().with_password(""))).unwrap();
+    assert_eq!(skipped.entries.len(), 2);
+    let result = inspect(&fixture("bundle.p12"), &options(Fake::new().with_password("")));
+    assert!(matches!(result, Err(Error::Input(InputError::Password { .. }))));
+
+    // only an unanswered prompt skips the check; a password source that was asked for must work
+    let unset = Options {
+        password: Password::Env("SSL_TEST_UNSET_PASSWORD".to_string()),
+        ..options(Fake::new())
+    };
+    let result = inspect(&fixture("truststore.jks"), &unset);
+    assert!(matches!(result, Err(Error::Input(InputError::PasswordUnset { .. }))));
+}
+
+#[test]
+fn validity_reports_the_soonest_expiring_keystore_entry() {
+    let protected = options(Fake::new().with_password("changeit"));
+    let soonest = validity(&fixture("keystore.jceks"), &protected).unwrap();
+    assert_eq!(soonest.len(), 1);
+    assert_eq!(soonest[0].alias.as_deref(), Some("server"));
+    assert_eq!(soonest[0].subject, "CN=p12.test");
+
+    let truststore = validity(&fixture("truststore.jks"), &protected).unwrap();
+    assert_eq!(truststore[0].alias.as_deref(), Some("expired"));
+    assert!(truststore[0].expired);
+
+    let indexed = Options {
+        select: Selection::Index(0),
+        ..protected
+    };
+    let first = validity(&fixture("keystore.jceks"), &indexed).unwrap();
+    assert_eq!(first[0].alias.as_deref(), Some("fixture root"));
+    assert!(validity(&fixture("leaf.pem"), &indexed).unwrap()[0].alias.is_none());
+
+    // a PKCS#12 archive holds one key's chain, so its leaf is reported even when its CA expires first
+    let archive = validity(&fixture("short-ca.p12"), &options(Fake::new())).unwrap();
+    assert_eq!(archive.len(), 1);
+    assert_eq!(archive[0].alias.as_deref(), Some("server"));
+    assert_eq!(archive[0].subject, "CN=long.p12.test");
+}
+
+#[test]
+fn verifies_and_matches_inputs_through_the_library() {
+    let store = TrustStore {
+        anchors: vec![Certificate::from_pem(&pem("root.pem")).unwrap()],
+    };
+    let options = options(Fake::new().with_pem("leaf.test", &pem("chain.pem")).unwrap());
+    let verification = verify("leaf.test", &store, None, &options).unwrap();
+    assert!(verification.is_valid(), "{}", verification);
+    assert_eq!(verification.path.len(), 3);
+
+    let verification = verify("leaf.test", &store, Some("other.test"), &options).unwrap();
+    assert_eq!(
+        verification.failures,
+        [Failure::NameMismatch {
+            name: "other.test".to_string()
+        }]
+    );
+
+    let untrusted = TrustStore {
+        anchors: vec![Certificate::from_pem(&pem("expired.pem")).unwrap()],
+    };
+    let verification = verify(&fixture("chain.pem"), &untrusted, None, &options).unwrap();
+    assert!(matches!(
+        verification.failures[..],
+        [Failure::UntrustedRoot { depth: 2, .. }]
+    ));
+
+    let intermediate = Options {
+        select: Selection::Index(1),
+        ..options.clone()
+    };
+    let verification = verify("leaf.test", &store, None, &intermediate).unwrap();
+    assert_eq!(verification.path[0].subject, "O=Fixture, CN=Fixture Intermediate CA");
+    assert!(!verification
+        .failures
+        .iter()
+        .any(|failure| matches!(failure, Failure::NameMismatch { .. })));
+    let all = Options {
+        select: Selection::All,
+        ..options.clone()
+    };
+    assert!(matches!(
+        verify("leaf.test", &store, None, &all),
+        Err(Error::Input(InputError::SelectAll { command: "verify" }))
+    ));
+
+    assert!(matches("leaf.test", "www.leaf.test", &options).unwrap().is_match());
+    assert!(!matches(&fixture("nosan.pem"), "nosan.test", &options)
+        .unwrap()
+        .is_match());
+}
+
+#[test]
+fn results_serialise_to_the_documented_json_schema() {
+    let options = options(Fake::new().with_pem("leaf.test", &pem("chain.pem")).unwrap());
+    let inspection = serde_json::to_value(inspect("leaf.test", &options).unwrap()).unwrap();
+    let mut keys: Vec<&str> = inspection.as_object().unwrap().keys().map(String::as_str).collect();
+    keys.sort_unstable();
+    assert_eq!(
+        keys,
+        [
+            "certificates",
+            "chain",
+            "entries",
+            "format",
+            "name_match",
+            "server_name",
+            "session"
+        ]
+    );
+    assert_eq!(inspection["session"]["protocol"], "TLSv1_3");
+    assert!(inspection["chain"].is_null());
+    assert_eq!(inspection["certificates"].as_array().unwrap().len(), 1);
+    assert_eq!(
+        inspection["certificates"][0]["sans"][2],
+        serde_json::json!({"type": "IP", "value": "127.0.0.1"})
+    );
+
+    let validity = serde_json::to_value(&validity("leaf.test", &options).unwrap()[0]).unwrap();
+    assert!(validity["alias"].is_null());
+    assert_eq!(validity["expired"], false);
+    assert!(validity["not_after"].as_str().unwrap().ends_with('Z'));
+
+    let name_match = serde_json::to_value(matches("leaf.test", "www.leaf.test", &options).unwrap()).unwrap();
+    assert_eq!(name_match["matched"], true);
+    assert_eq!(name_match["matched_by"], "DNS:*.leaf.test");
+}